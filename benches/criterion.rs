@@ -5,7 +5,7 @@ use test_constants::*;
 
 extern crate source_code_parser;
 use source_code_parser::{
-    ressa::{run_ressa_parse, NodePattern},
+    ressa::{run_ressa_parse, run_ressa_parse_parallel, NodePattern},
     *,
 };
 
@@ -44,6 +44,17 @@ fn ressa_benchmark(c: &mut Criterion, name: &str, ressa_json: &str, dir: &str) {
     });
 }
 
+fn ressa_parallel_benchmark(c: &mut Criterion, name: &str, ressa_json: &str, dir: &str) {
+    let dir = serde_json::from_str::<Directory>(dir).unwrap();
+    let ctx = parse_project_context(&dir).unwrap();
+    let ressa = serde_json::from_str::<Vec<NodePattern>>(ressa_json).unwrap();
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            let _ctx = black_box(run_ressa_parse_parallel(&ctx.modules, ressa.clone(), 0));
+        })
+    });
+}
+
 fn ressa_benchmark_endpoint_simple(c: &mut Criterion) {
     ressa_benchmark(
         c,
@@ -89,6 +100,15 @@ fn ressa_benchmark_entity_tt(c: &mut Criterion) {
     )
 }
 
+fn ressa_parallel_benchmark_entity_tt(c: &mut Criterion) {
+    ressa_parallel_benchmark(
+        c,
+        "ressa_parallel_entity_trainticket",
+        RESSA_JSON_ENTITY_TT,
+        &directory_json_tt(),
+    )
+}
+
 fn laast_benchmark_dsb(c: &mut Criterion) {
     let dir = serde_json::from_str::<Directory>(directory_json_dsb().as_str()).unwrap();
     laast_benchmark(c, "laast_deathstarbench", &dir)
@@ -136,6 +156,7 @@ criterion_group!(
     ressa_benchmark_endpoint,
     ressa_benchmark_entity,
     ressa_benchmark_endpoint_tt,
-    ressa_benchmark_entity_tt
+    ressa_benchmark_entity_tt,
+    ressa_parallel_benchmark_entity_tt
 );
 criterion_main!(benches);
@@ -0,0 +1,241 @@
+use serde::Serialize;
+use source_code_parser::{
+    ast::{walk_class, walk_expr, walk_method, CallExpr, DotExpr, Expr, ParenExpr, Visit},
+    ClassOrInterfaceComponent, Language, MethodComponent, ModuleComponent,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Calls between the methods of a project
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct CallGraph {
+    /// Methods, named by their enclosing classes and methods
+    pub nodes: BTreeSet<String>,
+    pub edges: BTreeSet<Edge>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+}
+
+/// Collects the methods called by each method, keyed by the method's name qualified by its
+/// enclosing classes and methods, like `com.shop.Class.method`
+#[derive(Default)]
+struct Calls {
+    scope: Vec<String>,
+    /// Types of the classes enclosing the visited node, innermost last
+    classes: Vec<String>,
+    /// Qualified name of the method being walked, if any
+    method: Option<String>,
+    /// The names each call may refer to, in the order they are looked up
+    callees: BTreeMap<String, BTreeSet<Vec<String>>>,
+}
+
+impl Visit for Calls {
+    fn visit_class(&mut self, class: &ClassOrInterfaceComponent) {
+        // Class names are already qualified by their enclosing classes
+        let class_type = class_type(class);
+        let outer_scope = std::mem::replace(&mut self.scope, vec![class_type.clone()]);
+        let outer_method = self.method.take();
+        self.classes.push(class_type);
+        walk_class(self, class);
+        self.classes.pop();
+        self.scope = outer_scope;
+        self.method = outer_method;
+    }
+
+    fn visit_method(&mut self, method: &MethodComponent) {
+        self.scope.push(method.method_name.clone());
+        let name = self.scope.join(".");
+        self.callees.entry(name.clone()).or_default();
+        let outer_method = self.method.replace(name);
+        walk_method(self, method);
+        self.method = outer_method;
+        self.scope.pop();
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let (Expr::CallExpr(CallExpr { name, .. }), Some(method)) = (expr, &self.method) {
+            let candidates = self.candidates(name);
+            if !candidates.is_empty() {
+                let callees = self.callees.entry(method.clone()).or_default();
+                callees.insert(candidates);
+            }
+        }
+        walk_expr(self, expr);
+    }
+}
+
+impl Calls {
+    /// Qualified names of the methods a call through `expr` may refer to, most specific first.
+    /// Calls on a receiver refer to a method of the receiver's type, and other calls to a method
+    /// of an enclosing class or to a function.
+    fn candidates(&self, expr: &Expr) -> Vec<String> {
+        match expr {
+            Expr::DotExpr(DotExpr { expr, selected, .. }) => {
+                match (self.receiver_type(expr), name(selected)) {
+                    (Some(receiver), Some(method)) => vec![format!("{}.{}", receiver, method)],
+                    _ => vec![],
+                }
+            }
+            expr => match name(expr) {
+                Some(function) => self
+                    .classes
+                    .iter()
+                    .rev()
+                    .map(|class| format!("{}.{}", class, function))
+                    .chain(std::iter::once(function.to_string()))
+                    .collect(),
+                None => vec![],
+            },
+        }
+    }
+
+    /// The class of a call's receiver, if its type is known
+    fn receiver_type(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Ident(ident) => ident.var_type.as_deref().map(class_name),
+            Expr::Literal(literal) if matches!(&*literal.value, "this" | "self") => {
+                self.classes.last().cloned()
+            }
+            Expr::ParenExpr(ParenExpr { expr, .. }) => self.receiver_type(expr),
+            _ => None,
+        }
+    }
+}
+
+/// Name of a called method or function, like `save`
+fn name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Ident(ident) => Some(ident.name.as_str()),
+        Expr::Literal(literal) => Some(literal.value.as_str()),
+        _ => None,
+    }
+}
+
+/// The type naming a class, which is qualified by its package in Java
+fn class_type(class: &ClassOrInterfaceComponent) -> String {
+    let package = &class.component.component.package_name;
+    match class.component.component.language {
+        Language::Java if !package.is_empty() => {
+            format!("{}.{}", package, class.component.container_name)
+        }
+        _ => class.component.container_name.clone(),
+    }
+}
+
+/// The class named by a type, without its type arguments, pointers and references
+fn class_name(r#type: &str) -> String {
+    r#type
+        .split('<')
+        .next()
+        .unwrap_or(r#type)
+        .trim_start_matches("const ")
+        .trim_end_matches(|c: char| matches!(c, '*' | '&' | ' '))
+        .to_string()
+}
+
+/// Build the call graph of the modules. Calls on a receiver are linked to the method of the
+/// receiver's type, as inferred while parsing, and other calls to the method of the innermost
+/// enclosing class declaring it, or else to the function. Calls whose receiver's type is
+/// unknown, and calls to methods outside the project, are left out. Overloads share a node.
+pub fn call_graph(modules: &[ModuleComponent]) -> CallGraph {
+    let mut calls = Calls::default();
+    for module in modules {
+        // Java modules also list the methods of their classes, which are walked with the class
+        if module.component.component.language != Language::Java {
+            for function in module.component.methods.iter() {
+                calls.visit_method(function);
+            }
+        }
+        for class in module.classes.iter().chain(module.interfaces.iter()) {
+            calls.visit_class(class);
+        }
+    }
+
+    let mut graph = CallGraph::default();
+    for (method, callees) in calls.callees.iter() {
+        graph.nodes.insert(method.clone());
+        for candidates in callees {
+            let callee = candidates
+                .iter()
+                .find(|candidate| calls.callees.contains_key(*candidate));
+            if let Some(to) = callee {
+                graph.edges.insert(Edge {
+                    from: method.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{call_graph, Edge};
+    use source_code_parser::{parse_directory_into_laast, Directory};
+    use tempfile::TempDir;
+
+    #[test]
+    fn links_calls_to_project_methods() {
+        let root = TempDir::new().unwrap();
+        std::fs::create_dir_all(root.path().join("order")).unwrap();
+        std::fs::write(
+            root.path().join("order/OrderService.java"),
+            r#"
+            class OrderService {
+                private OrderRepository repository;
+
+                public void place(Order order) {
+                    validate(order);
+                    repository.save(order);
+                    log.save(order);
+                }
+
+                private void validate(Order order) {
+                    System.out.println(order);
+                }
+            }
+
+            class OrderRepository {
+                public void save(Order order) {}
+            }
+
+            class AuditLog {
+                public void save(String entry) {}
+            }
+            "#,
+        )
+        .unwrap();
+        std::fs::write(root.path().join("notes.txt"), "not source").unwrap();
+
+        let modules = parse_directory_into_laast(&Directory::read(root.path()).unwrap()).unwrap();
+        let graph = call_graph(&modules);
+
+        assert_eq!(
+            vec![
+                "AuditLog.save",
+                "OrderRepository.save",
+                "OrderService.place",
+                "OrderService.validate"
+            ],
+            graph.nodes.iter().collect::<Vec<_>>()
+        );
+        // `log` is of unknown type, so its call is not linked to `AuditLog.save`
+        assert_eq!(
+            vec![
+                &Edge {
+                    from: "OrderService.place".into(),
+                    to: "OrderRepository.save".into()
+                },
+                &Edge {
+                    from: "OrderService.place".into(),
+                    to: "OrderService.validate".into()
+                }
+            ],
+            graph.edges.iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use clap::{Parser, Subcommand};
+use rust_code_analysis::AstPayload;
+use serde::Serialize;
+use source_code_parser::{
+    compat, parse_ast, parse_directory_into_laast_with_options, project_context,
+    ressa::{run_ressa_parse_parallel, run_ressa_parse_with_options, NodePattern, RessaOptions},
+    Directory, ParseOptions,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+mod graph;
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Opt {
+    #[clap(subcommand)]
+    command: Command,
+    /// File to write the JSON output to, instead of stdout
+    #[clap(long, short, global = true)]
+    output: Option<PathBuf>,
+    /// Define a macro for the C++ preprocessor, as `NAME` or `NAME=VALUE`
+    #[clap(
+        long = "define",
+        short = 'D',
+        global = true,
+        multiple_occurrences = true
+    )]
+    defines: Vec<String>,
+}
+
+impl Opt {
+    fn parse_options(&self) -> ParseOptions {
+        let defines = self
+            .defines
+            .iter()
+            .map(|define| match define.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => (define.clone(), String::new()),
+            })
+            .collect();
+        ParseOptions { defines }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the AST of a single source file
+    Ast { file: PathBuf },
+    /// Print the context (LAAST) of every source file under a directory
+    Ctx {
+        root: PathBuf,
+        /// Print the modules as NDJSON, one per line, instead of the whole context
+        #[clap(long)]
+        ndjson: bool,
+    },
+    /// Run ReSSA patterns over every source file under a directory
+    Ressa {
+        root: PathBuf,
+        /// JSON file holding the list of patterns to run
+        #[clap(long)]
+        patterns: PathBuf,
+        /// Report which match saved each object and attribute
+        #[clap(long)]
+        provenance: bool,
+        /// Report how each root pattern was applied
+        #[clap(long)]
+        trace: bool,
+        /// Split the modules between threads (0 for one per core); see `run_ressa_parse_parallel`
+        #[clap(long, conflicts_with_all = &["provenance", "trace"])]
+        threads: Option<usize>,
+    },
+    /// Print the call graph between the methods of every source file under a directory
+    Graph { root: PathBuf },
+}
+
+fn main() {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("warn"));
+    let opt = Opt::parse();
+    if let Err(err) = run(opt) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(opt: Opt) -> Result<(), Box<dyn Error>> {
+    let options = opt.parse_options();
+    let parse =
+        |root: PathBuf| parse_directory_into_laast_with_options(&Directory::read(root)?, &options);
+    match opt.command {
+        Command::Ast { file } => {
+            let code = std::fs::read_to_string(&file)?;
+            let result = parse_ast(AstPayload {
+                id: "".to_owned(),
+                file_name: file.to_str().unwrap_or_default().to_owned(),
+                code,
+                comment: false,
+                span: true,
+            });
+            match result {
+                Some((ast, _lang)) => write(&opt.output, &ast),
+                None => Err(format!("Invalid language: {:?}", file).into()),
+            }
+        }
+        Command::Ctx { root, ndjson: true } => {
+            let mut writer = open(&opt.output)?;
+            let mut converter = compat::ModuleConverter::default();
+            for module in parse(root)? {
+                serde_json::to_writer(&mut writer, &converter.convert(&module))?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+            Ok(())
+        }
+        Command::Ctx {
+            root,
+            ndjson: false,
+        } => {
+            let ctx = compat::JSSAContext::from(project_context(parse(root)?));
+            write(&opt.output, &ctx)
+        }
+        Command::Ressa {
+            root,
+            patterns,
+            provenance,
+            trace,
+            threads,
+        } => {
+            let patterns: Vec<NodePattern> =
+                serde_json::from_reader(io::BufReader::new(File::open(patterns)?))?;
+            let modules = parse(root)?;
+            if let Some(threads) = threads {
+                write(
+                    &opt.output,
+                    &run_ressa_parse_parallel(&modules, patterns, threads),
+                )
+            } else if provenance || trace {
+                let options = RessaOptions { provenance, trace };
+                write(
+                    &opt.output,
+                    &run_ressa_parse_with_options(&modules, patterns, options),
+                )
+            } else {
+                let report = run_ressa_parse_with_options(&modules, patterns, Default::default());
+                write(&opt.output, &report.result)
+            }
+        }
+        Command::Graph { root } => {
+            let modules = parse(root)?;
+            write(&opt.output, &graph::call_graph(&modules))
+        }
+    }
+}
+
+/// Write the data as JSON to the output file, or to stdout if there is none
+fn write<T: Serialize>(output: &Option<PathBuf>, data: &T) -> Result<(), Box<dyn Error>> {
+    let mut writer = open(output)?;
+    serde_json::to_writer(&mut writer, data)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Open the output file, or stdout if there is none
+fn open(output: &Option<PathBuf>) -> io::Result<BufWriter<Box<dyn Write>>> {
+    Ok(match output {
+        Some(path) => BufWriter::new(Box::new(File::create(Path::new(path))?)),
+        None => BufWriter::new(Box::new(io::stdout())),
+    })
+}
@@ -11,11 +11,25 @@ pub fn expand_derive(input: DeriveInput) -> TokenStream {
         get_struct_impl,
         |ident| quote! { #ident.get_fields() },
     );
+    let key_impl = util::get_impl(
+        &item_name,
+        &input.data,
+        |_, ident| {
+            // The name of the type as written, which is stable unlike `std::any::type_name`
+            let kind = ident.to_string();
+            quote! { (self as *const Self as usize, #kind) }
+        },
+        |ident| quote! { #ident.node_key() },
+    );
     quote! {
         impl source_code_parser::ressa::index::ChildFields for #item_name {
             fn get_fields(&self) -> std::vec::Vec<std::vec::Vec<&dyn source_code_parser::ressa::index::Indexable>> {
                 #fn_impl
             }
+
+            fn node_key(&self) -> (usize, &'static str) {
+                #key_impl
+            }
         }
     }
 }
@@ -102,7 +116,10 @@ fn get_indexable_field(path_segment: &PathSegment) -> Option<Vec<IndexableType>>
         | "AccessorType"
         | "ModuleStereotype"
         | "ContainerStereotype"
-        | "ContainerType" => None,
+        | "ContainerType"
+        | "MacroDefinition"
+        | "BaseClass"
+        | "Template" => None,
         "Node" | "Expr" | "Stmt" => Some(vec![IndexableType::Node(true)]),
         _ => Some(vec![IndexableType::Node(false)]),
     }
@@ -1,9 +1,13 @@
 use actix_web::{middleware::Logger, web, App, HttpServer};
 use clap::Parser;
+use std::sync::Mutex;
 
 mod routes;
 use routes::*;
 
+mod sessions;
+use sessions::Sessions;
+
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Opt {
@@ -11,6 +15,13 @@ struct Opt {
     host: String,
     #[clap(long, short, default_value = "8080")]
     port: i32,
+    /// Memory to keep parsed projects in between requests, in megabytes
+    #[clap(long, default_value = "1024")]
+    session_memory: usize,
+    /// Largest JSON request body to accept, in megabytes. Bodies sending the sources of a
+    /// project need to fit in it.
+    #[clap(long, default_value = "64")]
+    max_payload: usize,
 }
 
 #[actix_web::main]
@@ -18,13 +29,19 @@ async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
     let opt = Opt::parse();
     let addr = format!("{}:{}", opt.host, opt.port);
-    HttpServer::new(|| {
+    let sessions = web::Data::new(Mutex::new(Sessions::new(opt.session_memory * 1024 * 1024)));
+    let max_payload = opt.max_payload * 1024 * 1024;
+    HttpServer::new(move || {
         App::new()
             .service(ast)
             .service(ctx)
             .service(ressa)
+            .service(create_project)
+            .service(project_ressa)
+            .service(delete_project)
+            .app_data(sessions.clone())
             .wrap(Logger::default())
-            .app_data(web::JsonConfig::default().limit(1024 * 1024 * 4))
+            .app_data(web::JsonConfig::default().limit(max_payload))
     })
     .bind(addr)?
     .run()
@@ -1,58 +1,192 @@
-use actix_web::{post, web, HttpResponse};
+use actix_web::{delete, post, web, web::Bytes, HttpResponse};
+use futures_util::stream;
 use rust_code_analysis::AstPayload;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use source_code_parser::{
-    self, parse_ast, parse_project_context, parse_project_context_compat,
-    ressa::{run_ressa_parse, NodePattern},
-    Directory,
+    self, compat, parse_ast, parse_directory_into_laast_with_options,
+    parse_sources_into_laast_with_options, project_context,
+    ressa::{
+        run_ressa_parse, run_ressa_parse_with_index, run_ressa_parse_with_options, NodePattern,
+        RessaOptions,
+    },
+    Directory, JSSAContext, ParseOptions,
 };
+use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::fs::File;
-use std::io::prelude::*;
 use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::sessions::{Project, Sessions};
 
 #[derive(Deserialize)]
 pub struct AstRequest {
     file_path: String,
+    /// Contents of the file, to parse instead of reading `file_path` from the server's filesystem
+    #[serde(default)]
+    code: Option<String>,
+}
+
+/// A project to parse, with the options to parse it with, like `"defines": {"DEBUG": ""}`
+#[derive(Deserialize)]
+pub struct ProjectInput {
+    #[serde(flatten)]
+    files: ProjectFiles,
+    #[serde(flatten)]
+    options: ParseOptions,
+}
+
+/// The files of a project: either a directory on the server's filesystem, or the contents of its
+/// source files by path, like `{"sources": {"src/Main.java": "class Main {}"}}`
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ProjectFiles {
+    Sources { sources: BTreeMap<PathBuf, String> },
+    Directory(Directory),
+}
+
+impl ProjectInput {
+    fn parse_context(&self) -> std::io::Result<JSSAContext<'static>> {
+        let modules = match &self.files {
+            ProjectFiles::Sources { sources } => {
+                parse_sources_into_laast_with_options(sources, &self.options)?
+            }
+            ProjectFiles::Directory(directory) => {
+                parse_directory_into_laast_with_options(directory, &self.options)?
+            }
+        };
+        Ok(project_context(modules))
+    }
 }
 
+#[derive(Deserialize)]
+pub struct CtxOptions {
+    #[serde(default)]
+    stream: bool,
+}
+
+/// Query parameters of `/ctx`. With `?stream=true`, the project's modules are streamed as NDJSON,
+/// one module per line in the same shape as the context's `modules`, instead of returning the
+/// whole analysis context at once.
 #[post("/ctx")]
-pub async fn ctx(payload: web::Json<Directory>) -> HttpResponse {
-    match parse_project_context_compat(&payload) {
-        Ok(ctx) => ok(ctx),
+pub async fn ctx(
+    payload: web::Json<ProjectInput>,
+    options: web::Query<CtxOptions>,
+) -> HttpResponse {
+    match payload.parse_context() {
+        Ok(ctx) if options.stream => {
+            let mut converter = compat::ModuleConverter::default();
+            ndjson(
+                ctx.modules
+                    .into_iter()
+                    .map(move |module| converter.convert(&module)),
+            )
+        }
+        Ok(ctx) => ok(compat::JSSAContext::from(ctx)),
         Err(err) => internal_server_error(err),
     }
 }
 
 #[derive(Deserialize)]
 pub struct RessaInput {
-    project_dir: Directory,
+    project_dir: ProjectInput,
     patterns: Vec<NodePattern>,
 }
 
+/// Query parameters of `/ressa`. Requesting any extra reporting (`?provenance=true`,
+/// `?trace=true`) wraps the result as `{"result": ..., "provenance": ..., "trace": ...}`.
 #[post("/ressa")]
-pub async fn ressa(payload: web::Json<RessaInput>) -> HttpResponse {
-    match parse_project_context(&payload.project_dir) {
-        Ok(mut context) => ok(run_ressa_parse(
+pub async fn ressa(
+    payload: web::Json<RessaInput>,
+    options: web::Query<RessaOptions>,
+) -> HttpResponse {
+    let mut context = match payload.project_dir.parse_context() {
+        Ok(context) => context,
+        Err(err) => return internal_server_error(err),
+    };
+    if options.provenance || options.trace {
+        ok(run_ressa_parse_with_options(
+            &context.modules,
+            payload.patterns.clone(),
+            *options,
+        ))
+    } else {
+        ok(run_ressa_parse(
             &mut context.modules,
             payload.patterns.clone(),
-        )),
-        Err(err) => internal_server_error(err),
+        ))
     }
 }
 
-#[post("/ast")]
-pub async fn ast(payload: web::Json<AstRequest>) -> HttpResponse {
-    let mut code = String::new();
-    let path = PathBuf::from(&payload.file_path);
-    let mut file = match File::open(path) {
-        Ok(file) => file,
+/// Parse a project and keep it for later `/projects/{id}/ressa` requests, returning its id
+#[post("/projects")]
+pub async fn create_project(
+    payload: web::Json<ProjectInput>,
+    sessions: web::Data<Mutex<Sessions>>,
+) -> HttpResponse {
+    let context = match payload.parse_context() {
+        Ok(context) => context,
         Err(err) => return internal_server_error(err),
     };
-    if let Err(err) = file.read_to_string(&mut code) {
-        return internal_server_error(err);
+    let project = Project::from_modules(context.modules);
+    let size = project.size();
+    match sessions.lock().unwrap().insert(project, size) {
+        Some(id) => ok(json!({ "id": id })),
+        None => HttpResponse::PayloadTooLarge().json(json!({
+            "status": 413,
+            "message": "Project exceeds the session memory cap",
+        })),
     }
+}
+
+/// Run patterns against a parsed project, with the same query parameters and response as `/ressa`
+#[post("/projects/{id}/ressa")]
+pub async fn project_ressa(
+    id: web::Path<u64>,
+    patterns: web::Json<Vec<NodePattern>>,
+    options: web::Query<RessaOptions>,
+    sessions: web::Data<Mutex<Sessions>>,
+) -> HttpResponse {
+    // Release the lock before running, so other sessions are not held up
+    let project = match sessions.lock().unwrap().get(*id) {
+        Some(project) => project,
+        None => return not_found(*id),
+    };
+    let report = run_ressa_parse_with_index(
+        project.modules(),
+        project.index(),
+        patterns.into_inner(),
+        *options,
+    );
+    if options.provenance || options.trace {
+        ok(report)
+    } else {
+        ok(report.result)
+    }
+}
+
+/// Forget a parsed project
+#[delete("/projects/{id}")]
+pub async fn delete_project(
+    id: web::Path<u64>,
+    sessions: web::Data<Mutex<Sessions>>,
+) -> HttpResponse {
+    if sessions.lock().unwrap().remove(*id) {
+        HttpResponse::NoContent().finish()
+    } else {
+        not_found(*id)
+    }
+}
+
+#[post("/ast")]
+pub async fn ast(payload: web::Json<AstRequest>) -> HttpResponse {
+    let code = match &payload.code {
+        Some(code) => code.clone(),
+        None => match std::fs::read_to_string(&payload.file_path) {
+            Ok(code) => code,
+            Err(err) => return internal_server_error(err),
+        },
+    };
 
     let result = parse_ast(AstPayload {
         id: "".to_owned(),
@@ -73,6 +207,26 @@ fn ok<T: Serialize>(data: T) -> HttpResponse {
     HttpResponse::Ok().json(resp)
 }
 
+/// Stream the items as NDJSON, serializing each only when it is sent
+fn ndjson<T: Serialize, I: Iterator<Item = T> + 'static>(items: I) -> HttpResponse {
+    let lines = items.map(|item| {
+        let mut line = serde_json::to_vec(&item)?;
+        line.push(b'\n');
+        Ok::<_, serde_json::Error>(Bytes::from(line))
+    });
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(stream::iter(lines))
+}
+
+fn not_found(id: u64) -> HttpResponse {
+    let resp = json!({
+        "status": 404,
+        "message": format!("No project {}", id),
+    });
+    HttpResponse::NotFound().json(resp)
+}
+
 fn internal_server_error<E: Debug>(err: E) -> HttpResponse {
     let resp = json!({
         "status": 500,
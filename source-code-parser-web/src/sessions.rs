@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ouroboros::self_referencing;
+use source_code_parser::{
+    ressa::{index_modules, LaastIndex},
+    ModuleComponent,
+};
+
+/// Approximate memory use of a LAAST node, with its share of the index
+const BYTES_PER_NODE: usize = 256;
+
+/// A parsed project kept between requests with its index, so patterns can be run against it
+/// repeatedly
+#[self_referencing]
+pub struct Project {
+    modules: Vec<ModuleComponent>,
+    #[borrows(modules)]
+    #[covariant]
+    index: LaastIndex<'this>,
+}
+
+impl Project {
+    pub fn from_modules(modules: Vec<ModuleComponent>) -> Project {
+        ProjectBuilder {
+            modules,
+            index_builder: |modules| index_modules(modules),
+        }
+        .build()
+    }
+
+    pub fn modules(&self) -> &[ModuleComponent] {
+        self.borrow_modules()
+    }
+
+    pub fn index(&self) -> &LaastIndex<'_> {
+        self.borrow_index()
+    }
+
+    /// Approximate memory use of the project, from the number of nodes in its index
+    pub fn size(&self) -> usize {
+        self.index().len() * BYTES_PER_NODE
+    }
+}
+
+struct Session {
+    project: Arc<Project>,
+    size: usize,
+    /// Value of the clock when the session was last used
+    last_used: u64,
+}
+
+/// Parsed projects by id, evicting the least recently used once they would exceed the memory cap
+pub struct Sessions {
+    sessions: HashMap<u64, Session>,
+    next_id: u64,
+    clock: u64,
+    used: usize,
+    capacity: usize,
+}
+
+impl Sessions {
+    /// Create an empty set of sessions, holding up to `capacity` bytes of projects
+    pub fn new(capacity: usize) -> Sessions {
+        Sessions {
+            sessions: HashMap::new(),
+            next_id: 0,
+            clock: 0,
+            used: 0,
+            capacity,
+        }
+    }
+
+    /// Start a session for the project, evicting others to make room. Returns `None` if the
+    /// project alone exceeds the memory cap.
+    pub fn insert(&mut self, project: Project, size: usize) -> Option<u64> {
+        if size > self.capacity {
+            return None;
+        }
+        while self.used + size > self.capacity {
+            self.evict_least_recently_used();
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.clock += 1;
+        self.used += size;
+        self.sessions.insert(
+            id,
+            Session {
+                project: Arc::new(project),
+                size,
+                last_used: self.clock,
+            },
+        );
+        Some(id)
+    }
+
+    /// Retrieve a session's project, marking it as recently used
+    pub fn get(&mut self, id: u64) -> Option<Arc<Project>> {
+        self.clock += 1;
+        let session = self.sessions.get_mut(&id)?;
+        session.last_used = self.clock;
+        Some(session.project.clone())
+    }
+
+    /// End a session. Returns whether it existed.
+    pub fn remove(&mut self, id: u64) -> bool {
+        match self.sessions.remove(&id) {
+            Some(session) => {
+                self.used -= session.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .sessions
+            .iter()
+            .min_by_key(|(_, session)| session.last_used)
+            .map(|(id, _)| *id);
+        if let Some(id) = oldest {
+            tracing::info!("Evicting project session {}", id);
+            self.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Project, Sessions};
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut sessions = Sessions::new(100);
+        let first = sessions.insert(Project::from_modules(vec![]), 40).unwrap();
+        let second = sessions.insert(Project::from_modules(vec![]), 40).unwrap();
+        assert!(sessions.get(first).is_some());
+
+        // The second project is now the least recently used
+        let third = sessions.insert(Project::from_modules(vec![]), 40).unwrap();
+        assert!(sessions.get(second).is_none());
+        assert!(sessions.get(first).is_some());
+        assert!(sessions.get(third).is_some());
+
+        assert!(sessions
+            .insert(Project::from_modules(vec![]), 101)
+            .is_none());
+        assert!(sessions.remove(first));
+        assert!(!sessions.remove(first));
+        assert_eq!(40, sessions.used);
+    }
+}
@@ -29,6 +29,8 @@ pub enum Expr {
     Literal(Literal),
     SwitchExpr(SwitchExpr),
     CaseExpr(CaseExpr),
+    InstanceOfExpr(InstanceOfExpr),
+    MethodRefExpr(MethodRefExpr),
 }
 
 impl Expr {
@@ -51,6 +53,8 @@ impl Expr {
             Literal(e) => e.language,
             SwitchExpr(e) => e.language,
             CaseExpr(e) => e.language,
+            InstanceOfExpr(e) => e.language,
+            MethodRefExpr(e) => e.language,
         }
     }
 }
@@ -140,6 +144,10 @@ pub struct DotExpr {
 #[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
 pub struct Ident {
     pub name: String,
+    /// The type of the named variable, when it could be inferred.
+    #[new(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub var_type: Option<String>,
     #[new(value = r#""ident_expr""#)]
     r#type: &'static str,
     pub language: Language,
@@ -258,3 +266,23 @@ pub struct CaseExpr {
     r#type: &'static str,
     pub language: Language,
 }
+
+/// Represents a type test, like Java's instanceof
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
+pub struct InstanceOfExpr {
+    pub expr: Box<Expr>,
+    pub checked_type: String,
+    #[new(value = r#""instance_of_expr""#)]
+    r#type: &'static str,
+    pub language: Language,
+}
+
+/// Represents a reference to a method (or constructor) without calling it, like `Foo::bar`
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
+pub struct MethodRefExpr {
+    pub expr: Box<Expr>,
+    pub method: String,
+    #[new(value = r#""method_ref_expr""#)]
+    r#type: &'static str,
+    pub language: Language,
+}
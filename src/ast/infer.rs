@@ -0,0 +1,599 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::java::util::vartype::NO_TYPE;
+use crate::{ClassOrInterfaceComponent, MethodComponent, ModuleComponent};
+
+/// Infers the types of the variables declared without one, like C++ `auto` and Java `var`, from
+/// the expression initializing them: constructor calls (`new Foo()`), other variables, fields and
+/// the return types of the project's methods. The types are stored on the declarations, and on
+/// each `Ident` naming a variable (or an implicitly accessed field) of known type, so that the
+/// receivers of calls can be told apart. Inference does not leave a method, and only follows
+/// the types of values the way they are declared in the project.
+pub fn infer_types(modules: &mut [ModuleComponent]) {
+    let mut types = TypeTable::default();
+    for module in modules.iter() {
+        for class in module.classes.iter().chain(module.interfaces.iter()) {
+            types.add_class(class);
+        }
+        for function in module.component.methods.iter() {
+            if class_method(module, function).is_some() {
+                continue;
+            }
+            if let Some(return_type) = known_return_type(function) {
+                let name = match module.namespace.as_deref() {
+                    Some(namespace) if !namespace.is_empty() => {
+                        format!("{}::{}", namespace, function.method_name)
+                    }
+                    _ => function.method_name.clone(),
+                };
+                types.function_names.insert(name, types.functions.len());
+                types.functions.push(return_type);
+            }
+        }
+    }
+
+    let mut inference = TypeInference {
+        types: &types,
+        classes: vec![],
+        scopes: vec![],
+    };
+    for module in modules.iter_mut() {
+        for class in module
+            .classes
+            .iter_mut()
+            .chain(module.interfaces.iter_mut())
+        {
+            inference.visit_class(class);
+        }
+        for i in 0..module.component.methods.len() {
+            // The methods of classes are also listed by their module, and inferred in their class
+            match class_method(module, &module.component.methods[i]) {
+                Some(declared) => module.component.methods[i] = declared.clone(),
+                None => inference.visit_method(&mut module.component.methods[i]),
+            }
+        }
+    }
+}
+
+/// The method of a class of the module which a method listed by the module is a copy of
+fn class_method<'a>(
+    module: &'a ModuleComponent,
+    method: &MethodComponent,
+) -> Option<&'a MethodComponent> {
+    module
+        .classes
+        .iter()
+        .chain(module.interfaces.iter())
+        .flat_map(|class| class.component.methods.iter())
+        .find(|declared| {
+            declared.method_name == method.method_name && declared.line_begin == method.line_begin
+        })
+}
+
+/// The members of the project's classes, and its functions declared outside of them
+#[derive(Default)]
+struct TypeTable {
+    classes: Vec<ClassMembers>,
+    /// Index of each class in `classes`
+    names: Names,
+    /// Return types of the functions declared outside of classes
+    functions: Vec<String>,
+    /// Index of each function in `functions`
+    function_names: Names,
+}
+
+/// Indices of named things, by their qualified names and by their simple names. A simple name
+/// shared by several of them is ambiguous, so it refers to none.
+#[derive(Default)]
+struct Names {
+    qualified: HashMap<String, usize>,
+    simple: HashMap<String, Option<usize>>,
+}
+
+impl Names {
+    fn insert(&mut self, name: String, index: usize) {
+        self.simple
+            .entry(simple_name(&name).to_string())
+            .and_modify(|existing| {
+                if *existing != Some(index) {
+                    *existing = None;
+                }
+            })
+            .or_insert(Some(index));
+        self.qualified.entry(name).or_insert(index);
+    }
+
+    /// Find the index of a qualified name, or of the only one with its simple name
+    fn get(&self, name: &str) -> Option<usize> {
+        match self.qualified.get(name) {
+            Some(index) => Some(*index),
+            None => self.simple.get(simple_name(name)).copied().flatten(),
+        }
+    }
+}
+
+struct ClassMembers {
+    /// The type naming the class
+    name: String,
+    fields: HashMap<String, String>,
+    /// Return types of the methods, by name
+    methods: HashMap<String, String>,
+}
+
+impl TypeTable {
+    fn add_class(&mut self, class: &ClassOrInterfaceComponent) {
+        let name = class_type(class);
+        let mut members = ClassMembers {
+            name: name.clone(),
+            fields: HashMap::new(),
+            methods: HashMap::new(),
+        };
+        for field in class.field_components.iter() {
+            if is_known(&field.r#type) {
+                members
+                    .fields
+                    .insert(field.field_name.clone(), field.r#type.clone());
+            }
+        }
+        for method in class.component.methods.iter() {
+            if let Some(return_type) = known_return_type(method) {
+                members
+                    .methods
+                    .entry(method.method_name.clone())
+                    .or_insert(return_type);
+            }
+        }
+
+        self.names.insert(name, self.classes.len());
+        self.classes.push(members);
+
+        for inner_class in class.inner_classes.iter() {
+            self.add_class(inner_class);
+        }
+    }
+
+    /// Find the class a type refers to, ignoring its type arguments, pointers and references
+    fn class(&self, r#type: &str) -> Option<&ClassMembers> {
+        let name = r#type
+            .split('<')
+            .next()?
+            .trim_start_matches("const ")
+            .trim_end_matches(['*', '&', '[', ']', ' ']);
+        self.classes.get(self.names.get(name)?)
+    }
+
+    /// Find the return type of a function declared outside of classes
+    fn function(&self, name: &str) -> Option<&String> {
+        self.functions.get(self.function_names.get(name)?)
+    }
+}
+
+/// The type naming a class, which is qualified by its package in Java
+fn class_type(class: &ClassOrInterfaceComponent) -> String {
+    let package = &class.component.component.package_name;
+    match class.component.component.language {
+        Language::Java if !package.is_empty() => {
+            format!("{}.{}", package, class.component.container_name)
+        }
+        _ => class.component.container_name.clone(),
+    }
+}
+
+/// The name of a type without its namespace, package or enclosing classes
+fn simple_name(r#type: &str) -> &str {
+    r#type.rsplit(['.', ':']).next().unwrap_or(r#type)
+}
+
+fn known_return_type(method: &MethodComponent) -> Option<String> {
+    if is_known(&method.return_type) && method.return_type != "void" {
+        Some(method.return_type.clone())
+    } else {
+        None
+    }
+}
+
+/// Whether a declared type names a type, rather than leaving it to be inferred
+fn is_known(r#type: &str) -> bool {
+    match r#type.trim() {
+        "" | NO_TYPE | "var" => false,
+        r#type => !r#type
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .any(|word| word == "auto"),
+    }
+}
+
+/// The type of the elements of an array or of a container of a single type, like `List<Foo>`
+fn element_type(r#type: &str) -> Option<String> {
+    let r#type = r#type.trim_end_matches(['*', '&', ' ']);
+    if let Some(element) = r#type.strip_suffix("[]") {
+        return Some(element.to_string());
+    }
+    let (_, arguments) = r#type.split_once('<')?;
+    let arguments = arguments.strip_suffix('>')?;
+    let mut depth = 0;
+    for c in arguments.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    Some(arguments.trim().to_string())
+}
+
+struct TypeInference<'a> {
+    types: &'a TypeTable,
+    /// Types of the classes enclosing the visited node, innermost last
+    classes: Vec<String>,
+    /// Types of the variables declared in the enclosing methods and blocks, innermost last. The
+    /// variables of unknown type are kept too, as they shadow the fields named like them.
+    scopes: Vec<HashMap<String, Option<String>>>,
+}
+
+impl TypeInference<'_> {
+    fn declare(&mut self, name: &str, r#type: Option<&str>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), r#type.map(str::to_string));
+        }
+    }
+
+    /// Find the type of a variable, or of a field of the enclosing classes
+    fn lookup(&self, name: &str) -> Option<String> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(r#type) => r#type.clone(),
+            None => self
+                .classes
+                .iter()
+                .rev()
+                .find_map(|class| self.types.class(class)?.fields.get(name))
+                .cloned(),
+        }
+    }
+
+    /// Infer the type of an expression whose children were visited already
+    fn type_of(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Ident(ident) => ident.var_type.clone(),
+            Expr::Literal(literal) if matches!(&*literal.value, "this" | "self") => {
+                self.classes.last().cloned()
+            }
+            Expr::ParenExpr(ParenExpr { expr, .. }) => self.type_of(expr),
+            Expr::DotExpr(DotExpr { selected, .. }) => self.type_of(selected),
+            Expr::CallExpr(call) => self.call_type(call),
+            _ => None,
+        }
+    }
+
+    fn call_type(&self, call: &CallExpr) -> Option<String> {
+        match &*call.name {
+            // C++ allocations wrap the construction of their object
+            Expr::Literal(literal) if literal.value == "new" => match call.args.first()? {
+                Expr::CallExpr(init) => Some(format!("{}*", self.call_type(init)?)),
+                _ => None,
+            },
+            // Calls to a method of the receiver
+            Expr::DotExpr(DotExpr { expr, selected, .. }) => {
+                let method = match &**selected {
+                    Expr::Ident(Ident { name, .. })
+                    | Expr::Literal(Literal { value: name, .. }) => name,
+                    _ => return None,
+                };
+                let receiver = self.type_of(expr)?;
+                self.types.class(&receiver)?.methods.get(method).cloned()
+            }
+            // Calls to a method of an enclosing class, a function, or a constructor
+            Expr::Ident(Ident { name, language, .. }) => self
+                .classes
+                .iter()
+                .rev()
+                .find_map(|class| self.types.class(class)?.methods.get(name))
+                .or_else(|| self.types.function(name))
+                .cloned()
+                .or_else(|| {
+                    self.types
+                        .class(name)
+                        .map(|class| class.name.clone())
+                        // Java method calls always have a receiver, so the rest are constructors
+                        .or_else(|| match language {
+                            Language::Java => Some(name.clone()),
+                            _ => None,
+                        })
+                }),
+            _ => None,
+        }
+    }
+}
+
+impl VisitMut for TypeInference<'_> {
+    fn visit_block(&mut self, block: &mut Block) {
+        self.scopes.push(HashMap::new());
+        walk_block_mut(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::DeclStmt(decl) => {
+                for expr in decl.expressions.iter_mut().flatten() {
+                    self.visit_expr(expr);
+                }
+                for (i, var_decl) in decl.variables.iter_mut().enumerate() {
+                    if !var_decl.var_type.as_deref().is_some_and(is_known) {
+                        let init = decl.expressions.get(i).and_then(Option::as_ref);
+                        if let Some(r#type) = init.and_then(|init| self.type_of(init)) {
+                            var_decl.var_type = Some(r#type);
+                        }
+                    }
+                    self.visit_var_decl(var_decl);
+                }
+            }
+            Stmt::ForRangeStmt(for_range) => {
+                if let Some(iterator) = for_range.iterator.as_mut() {
+                    self.visit_expr(iterator);
+                }
+                let element = for_range
+                    .iterator
+                    .as_ref()
+                    .and_then(|iterator| self.type_of(iterator))
+                    .and_then(|r#type| element_type(&r#type));
+                if let (Stmt::DeclStmt(decl), Some(element)) = (&mut *for_range.init, element) {
+                    for var_decl in decl.variables.iter_mut() {
+                        if !var_decl.var_type.as_deref().is_some_and(is_known) {
+                            var_decl.var_type = Some(element.clone());
+                        }
+                    }
+                }
+
+                // The loop variable is only visible in the loop
+                self.scopes.push(HashMap::new());
+                self.visit_stmt(&mut for_range.init);
+                self.visit_block(&mut for_range.body);
+                self.scopes.pop();
+            }
+            stmt => walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Ident(ident) => {
+                if ident.var_type.is_none() {
+                    ident.var_type = self.lookup(&ident.name);
+                }
+            }
+            // The selected field is looked up in the class of the expression, not in scope
+            Expr::DotExpr(dot) => {
+                self.visit_expr(&mut dot.expr);
+                match &mut *dot.selected {
+                    Expr::Ident(field) => {
+                        if field.var_type.is_none() {
+                            field.var_type = self.type_of(&dot.expr).and_then(|owner| {
+                                self.types.class(&owner)?.fields.get(&field.name).cloned()
+                            });
+                        }
+                    }
+                    selected => self.visit_expr(selected),
+                }
+            }
+            Expr::LambdaExpr(_) => {
+                self.scopes.push(HashMap::new());
+                walk_expr_mut(self, expr);
+                self.scopes.pop();
+            }
+            expr => walk_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &mut VarDecl) {
+        let r#type = var_decl.var_type.clone().filter(|r#type| is_known(r#type));
+        self.declare(&var_decl.ident.name, r#type.as_deref());
+        if r#type.is_some() {
+            var_decl.ident.var_type = r#type;
+        }
+    }
+
+    fn visit_class(&mut self, class: &mut ClassOrInterfaceComponent) {
+        self.classes.push(class_type(class));
+        walk_class_mut(self, class);
+        self.classes.pop();
+    }
+
+    fn visit_method(&mut self, method: &mut MethodComponent) {
+        self.scopes.push(HashMap::new());
+        for parameter in method.parameters.iter() {
+            let r#type = Some(&*parameter.r#type).filter(|r#type| is_known(r#type));
+            self.declare(&parameter.parameter_name, r#type);
+        }
+        walk_method_mut(self, method);
+        self.scopes.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::*;
+    use crate::test_util::parse_files;
+    use crate::MethodComponent;
+
+    const JAVA: &str = r#"
+package com.shop;
+
+public class Checkout {
+    private UserServiceClient users;
+
+    public Receipt pay(Cart cart) {
+        var receipt = new Receipt();
+        var total = cart.total();
+        var user = users.find();
+        for (var item : cart.items()) {
+            var copy = item;
+        }
+        return receipt;
+    }
+}
+
+class Cart {
+    public Money total() { return null; }
+    public List<Item> items() { return null; }
+}
+
+class UserServiceClient {
+    public User find() { return null; }
+}
+
+class Receipt {}
+class Money {}
+class User {}
+class Item {}
+"#;
+
+    const CPP: &str = r#"
+class Cart {
+public:
+    int Total() { return 0; }
+};
+
+Cart* MakeCart() {
+    return new Cart();
+}
+
+void Checkout() {
+    auto cart = new Cart();
+    auto other = MakeCart();
+    auto copy = other;
+}
+"#;
+
+    /// Collects the variables declared in a method, with their types
+    #[derive(Default)]
+    struct Declarations(Vec<(String, String)>);
+
+    impl VisitMut for Declarations {
+        fn visit_var_decl(&mut self, var_decl: &mut VarDecl) {
+            self.0.push((
+                var_decl.ident.name.clone(),
+                var_decl.var_type.clone().unwrap_or_default(),
+            ));
+        }
+    }
+
+    fn declarations(method: &mut MethodComponent) -> Vec<(String, String)> {
+        let mut declarations = Declarations::default();
+        declarations.visit_method(method);
+        declarations.0
+    }
+
+    fn expected(declarations: &[(&str, &str)]) -> Vec<(String, String)> {
+        declarations
+            .iter()
+            .map(|(name, r#type)| (name.to_string(), r#type.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn infers_java_local_variable_types() {
+        let mut modules = parse_files(&[("shop/Checkout.java", JAVA)]);
+        let pay = modules
+            .iter_mut()
+            .flat_map(|module| module.classes.iter_mut())
+            .find(|class| class.component.container_name == "Checkout")
+            .unwrap()
+            .component
+            .methods
+            .get_mut(0)
+            .unwrap();
+
+        assert_eq!(
+            expected(&[
+                ("receipt", "com.shop.Receipt"),
+                ("total", "com.shop.Money"),
+                ("user", "com.shop.User"),
+                ("item", "com.shop.Item"),
+                ("copy", "com.shop.Item"),
+            ]),
+            declarations(pay)
+        );
+    }
+
+    #[test]
+    fn untyped_locals_shadow_fields() {
+        let router = r#"
+package com.shop;
+
+class Router {
+    private UserServiceClient users;
+
+    public void route() {
+        var users = lookup();
+        var user = users.find();
+    }
+}
+"#;
+        let mut modules =
+            parse_files(&[("shop/Checkout.java", JAVA), ("shop/Router.java", router)]);
+        let route = modules
+            .iter_mut()
+            .flat_map(|module| module.classes.iter_mut())
+            .find(|class| class.component.container_name == "Router")
+            .unwrap()
+            .component
+            .methods
+            .get_mut(0)
+            .unwrap();
+
+        // The type of `users` is unknown, rather than that of the field
+        assert_eq!(
+            expected(&[("users", "var"), ("user", "var")]),
+            declarations(route)
+        );
+    }
+
+    #[test]
+    fn infers_cpp_auto_types() {
+        let mut modules = parse_files(&[("shop/checkout.cpp", CPP)]);
+        let checkout = modules
+            .iter_mut()
+            .flat_map(|module| module.component.methods.iter_mut())
+            .find(|method| method.method_name == "Checkout")
+            .unwrap();
+
+        assert_eq!(
+            expected(&[("cart", "Cart*"), ("other", "Cart*"), ("copy", "Cart*")]),
+            declarations(checkout)
+        );
+    }
+
+    #[test]
+    fn ambiguous_names_are_not_inferred() {
+        let code = r#"
+class Cart {};
+class Order {};
+
+namespace shop {
+Cart* Make() { return new Cart(); }
+}
+
+namespace orders {
+Order* Make() { return new Order(); }
+}
+
+void Checkout() {
+    auto made = Make();
+    auto cart = shop::Make();
+}
+"#;
+        let mut modules = parse_files(&[("shop/checkout.cpp", code)]);
+        let checkout = modules
+            .iter_mut()
+            .flat_map(|module| module.component.methods.iter_mut())
+            .find(|method| method.method_name == "Checkout")
+            .unwrap();
+
+        assert_eq!(
+            expected(&[("made", "auto"), ("cart", "Cart*")]),
+            declarations(checkout)
+        );
+    }
+}
@@ -11,6 +11,12 @@ pub use expr::*;
 mod op;
 pub use op::*;
 
+mod visit;
+pub use visit::*;
+
+mod infer;
+pub use infer::*;
+
 use source_code_parser_macro::ChildFields;
 use source_code_parser_macro::NodeLanguage;
 
@@ -1,4 +1,4 @@
-use crate::AnnotationComponent;
+use crate::{AnnotationComponent, ClassOrInterfaceComponent};
 
 use super::*;
 use derive_more::From;
@@ -29,6 +29,10 @@ pub enum Stmt {
     CatchStmt(CatchStmt),
     WithResourceStmt(WithResourceStmt),
     LabelStmt(LabelStmt),
+    GotoStmt(GotoStmt),
+    AssertStmt(AssertStmt),
+    SynchronizedStmt(SynchronizedStmt),
+    ClassDeclStmt(ClassDeclStmt),
 }
 
 impl Stmt {
@@ -51,6 +55,10 @@ impl Stmt {
             CatchStmt(s) => s.language,
             WithResourceStmt(s) => s.language,
             LabelStmt(s) => s.language,
+            GotoStmt(s) => s.language,
+            AssertStmt(s) => s.language,
+            SynchronizedStmt(s) => s.language,
+            ClassDeclStmt(s) => s.language,
         }
     }
 }
@@ -253,3 +261,41 @@ pub struct LabelStmt {
     pub label: String,
     pub language: Language,
 }
+
+/// Represents a jump to a label, as in goto
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
+pub struct GotoStmt {
+    pub label: String,
+    #[new(value = r#""goto_stmt""#)]
+    r#type: &'static str,
+    pub language: Language,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
+pub struct AssertStmt {
+    pub cond: Expr,
+    /// The detail reported when the assertion fails
+    pub message: Option<Expr>,
+    #[new(value = r#""assert_stmt""#)]
+    r#type: &'static str,
+    pub language: Language,
+}
+
+/// Represents a block executed while holding a lock, as in Java's synchronized
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
+pub struct SynchronizedStmt {
+    pub lock: Expr,
+    pub body: Block,
+    #[new(value = r#""synchronized_stmt""#)]
+    r#type: &'static str,
+    pub language: Language,
+}
+
+/// Represents a class declared inside of a body, like a Java local class
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new, NodeLanguage, ChildFields)]
+pub struct ClassDeclStmt {
+    pub class: Box<ClassOrInterfaceComponent>,
+    #[new(value = r#""class_decl_stmt""#)]
+    r#type: &'static str,
+    pub language: Language,
+}
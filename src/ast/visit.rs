@@ -0,0 +1,320 @@
+use super::*;
+use crate::{ClassOrInterfaceComponent, MethodComponent};
+
+/// Defines a visitor trait, and the functions walking the children of each node, over either
+/// shared or (given `mut`) mutable references to the nodes
+macro_rules! visitor {
+    (
+        $(#[$attr:meta])*
+        $Visit:ident {
+            $walk_block:ident,
+            $walk_node:ident,
+            $walk_decl:ident,
+            $walk_stmt:ident,
+            $walk_expr:ident,
+            $walk_call:ident,
+            $walk_case:ident,
+            $walk_class:ident,
+            $walk_method:ident $(,)?
+        }
+        $($mut:tt)?
+    ) => {
+        $(#[$attr])*
+        pub trait $Visit {
+            fn visit_block(&mut self, block: &$($mut)? Block) {
+                $walk_block(self, block)
+            }
+
+            fn visit_node(&mut self, node: &$($mut)? Node) {
+                $walk_node(self, node)
+            }
+
+            fn visit_stmt(&mut self, stmt: &$($mut)? Stmt) {
+                $walk_stmt(self, stmt)
+            }
+
+            fn visit_expr(&mut self, expr: &$($mut)? Expr) {
+                $walk_expr(self, expr)
+            }
+
+            fn visit_var_decl(&mut self, _var_decl: &$($mut)? VarDecl) {}
+
+            /// Visit a class declared in another class or in a body, like a Java local class
+            fn visit_class(&mut self, class: &$($mut)? ClassOrInterfaceComponent) {
+                $walk_class(self, class)
+            }
+
+            fn visit_method(&mut self, method: &$($mut)? MethodComponent) {
+                $walk_method(self, method)
+            }
+        }
+
+        pub fn $walk_block<V: $Visit + ?Sized>(visitor: &mut V, block: &$($mut)? Block) {
+            for node in &$($mut)? block.nodes {
+                visitor.visit_node(node);
+            }
+        }
+
+        pub fn $walk_node<V: $Visit + ?Sized>(visitor: &mut V, node: &$($mut)? Node) {
+            match node {
+                Node::Block(block) => visitor.visit_block(block),
+                Node::Stmt(stmt) => visitor.visit_stmt(stmt),
+                Node::Expr(expr) => visitor.visit_expr(expr),
+            }
+        }
+
+        pub fn $walk_decl<V: $Visit + ?Sized>(visitor: &mut V, decl: &$($mut)? DeclStmt) {
+            for var_decl in &$($mut)? decl.variables {
+                visitor.visit_var_decl(var_decl);
+            }
+            for expr in (&$($mut)? decl.expressions).into_iter().flatten() {
+                visitor.visit_expr(expr);
+            }
+        }
+
+        pub fn $walk_stmt<V: $Visit + ?Sized>(visitor: &mut V, stmt: &$($mut)? Stmt) {
+            match stmt {
+                Stmt::DeclStmt(decl) => $walk_decl(visitor, decl),
+                Stmt::ExprStmt(ExprStmt { expr, .. }) => visitor.visit_expr(expr),
+                Stmt::IfStmt(IfStmt {
+                    cond,
+                    body,
+                    else_body,
+                    ..
+                }) => {
+                    visitor.visit_expr(cond);
+                    visitor.visit_block(body);
+                    if let Some(else_body) = else_body {
+                        visitor.visit_block(else_body);
+                    }
+                }
+                Stmt::ForStmt(ForStmt {
+                    init,
+                    condition,
+                    post,
+                    body,
+                    ..
+                }) => {
+                    for stmt in init {
+                        visitor.visit_stmt(stmt);
+                    }
+                    if let Some(condition) = condition {
+                        visitor.visit_expr(condition);
+                    }
+                    for expr in post {
+                        visitor.visit_expr(expr);
+                    }
+                    visitor.visit_block(body);
+                }
+                Stmt::ForRangeStmt(ForRangeStmt {
+                    init,
+                    iterator,
+                    body,
+                    ..
+                }) => {
+                    if let Some(iterator) = iterator {
+                        visitor.visit_expr(iterator);
+                    }
+                    visitor.visit_stmt(init);
+                    visitor.visit_block(body);
+                }
+                Stmt::WhileStmt(WhileStmt {
+                    condition, body, ..
+                }) => {
+                    visitor.visit_expr(condition);
+                    visitor.visit_block(body);
+                }
+                Stmt::DoWhileStmt(DoWhileStmt {
+                    condition, body, ..
+                }) => {
+                    visitor.visit_block(body);
+                    visitor.visit_expr(condition);
+                }
+                Stmt::ReturnStmt(ReturnStmt { expr, .. }) | Stmt::ThrowStmt(ThrowStmt { expr, .. }) => {
+                    if let Some(expr) = expr {
+                        visitor.visit_expr(expr);
+                    }
+                }
+                Stmt::TryCatchStmt(TryCatchStmt {
+                    try_body,
+                    catch_bodies,
+                    finally_body,
+                    ..
+                }) => {
+                    visitor.visit_block(try_body);
+                    for catch in catch_bodies {
+                        $walk_decl(visitor, &$($mut)? catch.exc);
+                        visitor.visit_block(&$($mut)? catch.body);
+                    }
+                    if let Some(finally_body) = finally_body {
+                        visitor.visit_block(finally_body);
+                    }
+                }
+                Stmt::CatchStmt(CatchStmt { exc, body, .. }) => {
+                    $walk_decl(visitor, exc);
+                    visitor.visit_block(body);
+                }
+                Stmt::WithResourceStmt(WithResourceStmt {
+                    resources, body, ..
+                }) => {
+                    $walk_decl(visitor, resources);
+                    visitor.visit_block(body);
+                }
+                Stmt::AssertStmt(AssertStmt { cond, message, .. }) => {
+                    visitor.visit_expr(cond);
+                    if let Some(message) = message {
+                        visitor.visit_expr(message);
+                    }
+                }
+                Stmt::SynchronizedStmt(SynchronizedStmt { lock, body, .. }) => {
+                    visitor.visit_expr(lock);
+                    visitor.visit_block(body);
+                }
+                Stmt::ClassDeclStmt(ClassDeclStmt { class, .. }) => visitor.visit_class(class),
+                Stmt::ImportStmt(_)
+                | Stmt::BreakStmt(_)
+                | Stmt::ContinueStmt(_)
+                | Stmt::LabelStmt(_)
+                | Stmt::GotoStmt(_) => {}
+            }
+        }
+
+        pub fn $walk_expr<V: $Visit + ?Sized>(visitor: &mut V, expr: &$($mut)? Expr) {
+            match expr {
+                Expr::AssignExpr(AssignExpr { lhs, rhs, .. }) => {
+                    for expr in rhs {
+                        visitor.visit_expr(expr);
+                    }
+                    for expr in lhs {
+                        visitor.visit_expr(expr);
+                    }
+                }
+                Expr::BinaryExpr(BinaryExpr { lhs, rhs, .. }) => {
+                    visitor.visit_expr(lhs);
+                    visitor.visit_expr(rhs);
+                }
+                Expr::UnaryExpr(UnaryExpr { expr, .. })
+                | Expr::ParenExpr(ParenExpr { expr, .. })
+                | Expr::IncDecExpr(IncDecExpr { expr, .. })
+                | Expr::MethodRefExpr(MethodRefExpr { expr, .. }) => visitor.visit_expr(expr),
+                Expr::CallExpr(call) => $walk_call(visitor, call),
+                Expr::EndpointCallExpr(EndpointCallExpr { call_expr, .. }) => {
+                    $walk_call(visitor, call_expr)
+                }
+                Expr::IndexExpr(IndexExpr {
+                    expr, index_expr, ..
+                }) => {
+                    visitor.visit_expr(expr);
+                    visitor.visit_expr(index_expr);
+                }
+                Expr::DotExpr(DotExpr { expr, selected, .. }) => {
+                    visitor.visit_expr(expr);
+                    visitor.visit_expr(selected);
+                }
+                Expr::InitListExpr(InitListExpr { exprs: args, .. })
+                | Expr::LogExpr(LogExpr { args, .. }) => {
+                    for expr in args {
+                        visitor.visit_expr(expr);
+                    }
+                }
+                Expr::LambdaExpr(LambdaExpr {
+                    parameters, body, ..
+                }) => {
+                    for decl in parameters {
+                        $walk_decl(visitor, decl);
+                    }
+                    visitor.visit_block(body);
+                }
+                Expr::SwitchExpr(SwitchExpr {
+                    condition, cases, ..
+                }) => {
+                    visitor.visit_expr(condition);
+                    for case in cases {
+                        $walk_case(visitor, case);
+                    }
+                }
+                Expr::CaseExpr(case) => $walk_case(visitor, case),
+                Expr::InstanceOfExpr(InstanceOfExpr { expr, .. }) => visitor.visit_expr(expr),
+                Expr::Ident(_) | Expr::Literal(_) => {}
+            }
+        }
+
+        fn $walk_call<V: $Visit + ?Sized>(visitor: &mut V, call: &$($mut)? CallExpr) {
+            visitor.visit_expr(&$($mut)? call.name);
+            for expr in &$($mut)? call.args {
+                visitor.visit_expr(expr);
+            }
+        }
+
+        fn $walk_case<V: $Visit + ?Sized>(visitor: &mut V, case: &$($mut)? CaseExpr) {
+            if let Some(cond) = &$($mut)? case.cond {
+                visitor.visit_expr(cond);
+            }
+            visitor.visit_block(&$($mut)? case.body);
+        }
+
+        /// Walk the methods, constructors, field initializers and inner classes of a class
+        pub fn $walk_class<V: $Visit + ?Sized>(
+            visitor: &mut V,
+            class: &$($mut)? ClassOrInterfaceComponent,
+        ) {
+            for field in &$($mut)? class.field_components {
+                if let Some(expr) = &$($mut)? field.expression {
+                    visitor.visit_expr(expr);
+                }
+            }
+            for method in &$($mut)? class.constructors {
+                visitor.visit_method(method);
+            }
+            for method in &$($mut)? class.component.methods {
+                visitor.visit_method(method);
+            }
+            for inner_class in &$($mut)? class.inner_classes {
+                visitor.visit_class(inner_class);
+            }
+        }
+
+        /// Walk the body and sub-methods of a method
+        pub fn $walk_method<V: $Visit + ?Sized>(visitor: &mut V, method: &$($mut)? MethodComponent) {
+            if let Some(body) = &$($mut)? method.body {
+                visitor.visit_block(body);
+            }
+            for sub_method in &$($mut)? method.sub_methods {
+                visitor.visit_method(sub_method);
+            }
+        }
+    };
+}
+
+visitor! {
+    /// Visits the nodes of a tree in the order they appear. Every method defaults to walking the
+    /// children of its node, so implementors only override the nodes they are interested in
+    /// (calling the matching `walk_*` function to keep descending).
+    Visit {
+        walk_block,
+        walk_node,
+        walk_decl,
+        walk_stmt,
+        walk_expr,
+        walk_call,
+        walk_case,
+        walk_class,
+        walk_method,
+    }
+}
+
+visitor! {
+    /// Visits the nodes of a tree like `Visit`, allowing them to be modified
+    VisitMut {
+        walk_block_mut,
+        walk_node_mut,
+        walk_decl_mut,
+        walk_stmt_mut,
+        walk_expr_mut,
+        walk_call_mut,
+        walk_case_mut,
+        walk_class_mut,
+        walk_method_mut,
+    }
+    mut
+}
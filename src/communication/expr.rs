@@ -13,7 +13,8 @@ comm_repl_default_impl!(
     Ident,
     Literal,
     IndexExpr,
-    EndpointCallExpr
+    EndpointCallExpr,
+    MethodRefExpr
 );
 
 impl CommunicationReplacer for AssignExpr {
@@ -80,9 +81,11 @@ impl CommunicationReplacer for CallExpr {
 
         // Deduce information about the service being called by the client type or alternatively
         // the client variable's naming convention
-        let client_ident = match_ident_or(&*client_call.expr)?.name.to_lowercase();
+        let client = match_ident_or(&*client_call.expr)?;
+        let client_ident = client.name.to_lowercase();
         let method_ident = match_ident_or(&*client_call.selected)?;
-        let client_name = get_rpc_service_name(callee_class, &client_ident);
+        let client_name =
+            get_rpc_service_name(callee_class, &client_ident, client.var_type.as_deref());
         let client_name = match get_rest_service_name(callee_class, self.args.first()) {
             Some(rest_name) => Some(rest_name),
             None => client_name,
@@ -198,7 +201,13 @@ fn match_ident_or(expr: &Expr) -> Option<&Ident> {
 fn get_rpc_service_name(
     class: Option<&ClassOrInterfaceComponent>,
     client_ident: &str,
+    client_type: Option<&str>,
 ) -> Option<String> {
+    // Go by the type of the client when it is known
+    if let Some(client_name) = client_type.and_then(get_client_service_name) {
+        return Some(client_name);
+    }
+
     // Search through the modules pertaining to the client using the client name
     let mut client_name = client_ident
         .replace("client", "")
@@ -209,24 +218,31 @@ fn get_rpc_service_name(
             // Go by type information for finding the service name. If not available
             // use naming convention.
             let field_name = if !field.r#type.is_empty() {
-                field.r#type.to_lowercase()
+                &field.r#type
             } else {
-                field.field_name.to_lowercase()
+                &field.field_name
             };
-
-            if field_name.contains("client") {
-                // Strip excess type information and unneeded naming like "client" and underscores
-                let mut field_name = field_name.split("client").next()?;
-                if field_name.contains('<') {
-                    field_name = field_name.split('<').last()?;
-                }
-                client_name = field_name.replace("_", "").replace("service", "");
+            if let Some(field_client_name) = get_client_service_name(field_name) {
+                client_name = field_client_name;
             }
         }
     }
     Some(client_name)
 }
 
+/// Find the name of the service called by a client from the client's type or name
+/// (`UserServiceClient` and `user_service_client` are clients of `user`)
+fn get_client_service_name(client: &str) -> Option<String> {
+    // Strip excess type information and unneeded naming like "client" and underscores
+    let client = client.to_lowercase();
+    let client = client
+        .split(|c: char| matches!(c, '<' | '>' | ',' | '.' | ':' | '*' | '&' | ' '))
+        .filter(|part| part.contains("client"))
+        .last()?;
+    let service_name = client.split("client").next()?;
+    Some(service_name.replace("_", "").replace("service", ""))
+}
+
 fn get_rest_service_name(
     class: Option<&ClassOrInterfaceComponent>,
     arg: Option<&Expr>,
@@ -331,3 +347,21 @@ impl CommunicationReplacer for CaseExpr {
             .replace_communication_call(modules, module, class, method)
     }
 }
+
+impl CommunicationReplacer for InstanceOfExpr {
+    fn replace_communication_call(
+        &mut self,
+        modules: &[ModuleComponent],
+        module: &ModuleComponent,
+        class: Option<&ClassOrInterfaceComponent>,
+        method: &MethodComponent,
+    ) -> Option<Node> {
+        if let Some(Node::Expr(replacement)) = self
+            .expr
+            .replace_communication_call(modules, module, class, method)
+        {
+            *self.expr = replacement;
+        }
+        None
+    }
+}
@@ -3,7 +3,14 @@ use crate::comm_repl_default_impl;
 use crate::{ast::*, ClassOrInterfaceComponent};
 use crate::{prophet::ModuleComponent, MethodComponent};
 
-comm_repl_default_impl!(ImportStmt, BreakStmt, ContinueStmt, ThrowStmt, LabelStmt);
+comm_repl_default_impl!(
+    ImportStmt,
+    BreakStmt,
+    ContinueStmt,
+    ThrowStmt,
+    LabelStmt,
+    GotoStmt
+);
 
 impl CommunicationReplacer for DeclStmt {
     fn replace_communication_call(
@@ -197,3 +204,65 @@ impl CommunicationReplacer for WithResourceStmt {
         None
     }
 }
+
+impl CommunicationReplacer for AssertStmt {
+    fn replace_communication_call(
+        &mut self,
+        modules: &[ModuleComponent],
+        module: &ModuleComponent,
+        class: Option<&ClassOrInterfaceComponent>,
+        method: &MethodComponent,
+    ) -> Option<Node> {
+        if let Some(Node::Expr(replacement)) =
+            (&mut self.cond).replace_communication_call(modules, module, class, method)
+        {
+            self.cond = replacement;
+        }
+        if let Some(Node::Expr(replacement)) = self
+            .message
+            .as_mut()?
+            .replace_communication_call(modules, module, class, method)
+        {
+            self.message = Some(replacement);
+        }
+        None
+    }
+}
+
+impl CommunicationReplacer for SynchronizedStmt {
+    fn replace_communication_call(
+        &mut self,
+        modules: &[ModuleComponent],
+        module: &ModuleComponent,
+        class: Option<&ClassOrInterfaceComponent>,
+        method: &MethodComponent,
+    ) -> Option<Node> {
+        if let Some(Node::Expr(replacement)) =
+            (&mut self.lock).replace_communication_call(modules, module, class, method)
+        {
+            self.lock = replacement;
+        }
+        self.body
+            .replace_communication_call(modules, module, class, method)
+    }
+}
+
+impl CommunicationReplacer for ClassDeclStmt {
+    fn replace_communication_call(
+        &mut self,
+        modules: &[ModuleComponent],
+        module: &ModuleComponent,
+        _class: Option<&ClassOrInterfaceComponent>,
+        _method: &MethodComponent,
+    ) -> Option<Node> {
+        // The methods of the declared class are replaced in the scope of that class
+        let class_view = self.class.as_ref().clone();
+        for method in self.class.component.methods.iter_mut() {
+            let method_view = method.clone();
+            if let Some(body) = method.body.as_mut() {
+                body.replace_communication_call(modules, module, Some(&class_view), &method_view);
+            }
+        }
+        None
+    }
+}
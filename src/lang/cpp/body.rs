@@ -13,5 +13,5 @@ pub(crate) fn block_nodes(compound_statement: &AST) -> Vec<Node> {
 }
 
 pub(crate) fn block_nodes_iter(children: &[AST]) -> Vec<Node> {
-    children.iter().map(body_node).flatten().collect()
+    children.iter().flat_map(body_nodes).collect()
 }
@@ -129,16 +129,22 @@ fn index_expression(node: &AST) -> Option<IndexExpr> {
 
 fn new_expression(new_expr: &AST) -> Option<CallExpr> {
     let r#type = expression(new_expr.children.get(1)?)?;
-    let expr = expression(new_expr.children.last()?)?;
-    match expr {
-        Expr::IndexExpr(mut ndx) => {
-            ndx.expr = Box::new(r#type);
-            let new = Literal::new("new".into(), Cpp);
-            let init = CallExpr::new(Box::new(new.into()), vec![ndx.into()], Cpp);
-            Some(init)
+    let init: Expr = match new_expr.children.get(2) {
+        // Objects are constructed as a call to their type (`new Foo(bar)`)
+        Some(args) if matches!(&*args.r#type, "argument_list" | "initializer_list") => {
+            CallExpr::new(Box::new(r#type), arguments(args), Cpp).into()
         }
-        _ => None,
-    }
+        None => CallExpr::new(Box::new(r#type), vec![], Cpp).into(),
+        Some(_) => match expression(new_expr.children.last()?)? {
+            Expr::IndexExpr(mut ndx) => {
+                ndx.expr = Box::new(r#type);
+                ndx.into()
+            }
+            _ => return None,
+        },
+    };
+    let new = Literal::new("new".into(), Cpp);
+    Some(CallExpr::new(Box::new(new.into()), vec![init], Cpp))
 }
 
 fn delete_expression(delete_expr: &AST) -> Option<CallExpr> {
@@ -280,14 +286,18 @@ fn call_expression(node: &AST) -> Option<Expr> {
     let mut nodes = node.children.iter();
     // field_expression, identifier
     let function_name = expression(nodes.next()?)?;
-    let argument_list = nodes.next_back()?;
-    let args: Vec<Expr> = argument_list
+    let args = arguments(nodes.next_back()?);
+    Some(CallExpr::new(Box::new(function_name), args, Cpp).into())
+}
+
+/// Converts the expressions of an "argument_list" or "initializer_list"
+fn arguments(argument_list: &AST) -> Vec<Expr> {
+    argument_list
         .children
         .iter()
         .map(expression)
         .flatten()
-        .collect();
-    Some(CallExpr::new(Box::new(function_name), args, Cpp).into())
+        .collect()
 }
 
 fn lambda_expression(lambda_expr: &AST) -> Option<LambdaExpr> {
@@ -11,18 +11,19 @@ pub use expr::*;
 mod stmt;
 pub use stmt::*;
 
-pub fn merge_modules(modules: Vec<ModuleComponent>) -> Vec<ModuleComponent> {
-    let mut merged = vec![];
-    for mut module in modules {
-        // Need to merge functions into classes within a module as well..
-        merge_class_methods(&mut module);
+mod preproc;
+pub use preproc::*;
 
-        // First module is not a duplicate
-        if merged.is_empty() {
-            merged.push(module);
-            continue;
-        }
+mod resolve;
+pub use resolve::*;
+
+mod template;
 
+/// Merge the modules sharing a name (such as a namespace reopened across files), then pair the
+/// out-of-line method definitions with the classes declaring them
+pub fn merge_modules(modules: Vec<ModuleComponent>) -> Vec<ModuleComponent> {
+    let mut merged: Vec<ModuleComponent> = vec![];
+    for mut module in modules {
         let mergeable = merged
             .iter_mut()
             .find(|m| m.module_name == module.module_name);
@@ -35,73 +36,38 @@ pub fn merge_modules(modules: Vec<ModuleComponent>) -> Vec<ModuleComponent> {
                 .component
                 .methods
                 .append(&mut module.component.methods);
-
-            merge_class_methods(mergeable);
+            mergeable.imports.append(&mut module.imports);
+            mergeable.macros.append(&mut module.macros);
         } else {
             merged.push(module);
         }
     }
+    resolve_definitions(&mut merged);
     merged
 }
 
-fn merge_class_methods(module: &mut ModuleComponent) {
-    // Check if there are class methods declared in the functions
-    for class in module.classes.iter_mut() {
-        let functions: Vec<&mut MethodComponent> = module
-            .component
-            .methods
-            .iter_mut()
-            // Issue with merging... Trying to merge CastInfoServiceProcessor and CastInfoServiceProcessorFactory
-            .filter(|m| m.method_name.starts_with(&class.component.container_name))
-            .collect();
-
-        for function in functions {
-            let class_name = &*class.component.container_name;
-            let class_method = class.component.methods.iter_mut().find(|m| {
-                function.method_name == format!("{}::{}", class_name, &m.method_name)
-                    && m.parameters == function.parameters
-            });
-
-            if let Some(class_method) = class_method {
-                class_method.line_begin = function.line_begin;
-                class_method.line_end = function.line_end;
-                class_method.line_count = function.line_count;
-                class_method.body = function.body.clone();
-            }
-        }
-    }
-
-    // Filter out the class methods since the merge already occurred in the previous loop
-    module.component.methods = module
-        .component
-        .methods
-        .clone()
-        .into_iter()
-        .filter(|m| {
-            !module
-                .classes
-                .iter_mut()
-                .any(|class| m.method_name.starts_with(&class.component.container_name))
-        })
-        .collect();
-}
-
 pub fn find_components(ast: AST, module_name: &str, path: &str) -> Vec<ComponentType> {
     match &*ast.r#type {
-        "namespace_definition" => match transform_namespace_to_module(ast, path) {
-            Some(module) => vec![ComponentType::ModuleComponent(module)],
-            None => vec![],
-        },
+        "namespace_definition" => transform_namespace(ast, module_name, None, path),
         "function_definition" => match transform_into_method(&ast, module_name, path) {
             Some(method) => vec![ComponentType::MethodComponent(method)],
             None => vec![],
         },
+        "template_declaration" => template::transform_template(ast, module_name, path),
         "class_specifier" | "struct_specifier" | "type_definition" => {
-            match transform_into_class(&ast, module_name, path) {
+            match transform_into_nested_class(&ast, None, module_name, path) {
                 Some(class) => vec![ComponentType::ClassOrInterfaceComponent(class)],
                 None => vec![],
             }
         }
+        "preproc_include" => match include_statement(&ast) {
+            Some(import) => vec![ComponentType::ImportStmt(import)],
+            None => vec![],
+        },
+        "preproc_def" | "preproc_function_def" => match macro_definition(&ast) {
+            Some(definition) => vec![ComponentType::MacroDefinition(definition)],
+            None => vec![],
+        },
         _ => {
             let components: Vec<ComponentType> = ast
                 .children
@@ -113,18 +79,52 @@ pub fn find_components(ast: AST, module_name: &str, path: &str) -> Vec<Component
     }
 }
 
-fn transform_namespace_to_module(ast: AST, path: &str) -> Option<ModuleComponent> {
-    let name = ast
+/// Transforms a namespace into a module named after its qualified name (`a::b`), followed by the
+/// modules of the namespaces nested in it. The items of an anonymous namespace are returned as
+/// they are, but private to their file.
+fn transform_namespace(
+    ast: AST,
+    module_name: &str,
+    namespace: Option<&str>,
+    path: &str,
+) -> Vec<ComponentType> {
+    // The grammar does not support C++17 nested namespace definitions (`namespace a::b`), and
+    // leaves the rest of the name in an error node
+    let name: Vec<_> = ast
         .children
         .iter()
-        .find(|child| child.r#type == "identifier")?
-        .value
-        .clone();
+        .flat_map(|child| match &*child.r#type {
+            "identifier" => vec![&*child.value],
+            "ERROR" => child
+                .children
+                .iter()
+                .filter(|name| name.r#type == "identifier")
+                .map(|name| &*name.value)
+                .collect(),
+            _ => vec![],
+        })
+        .collect();
+
+    if name.is_empty() {
+        let mut components: Vec<_> = ast
+            .children
+            .into_iter()
+            .flat_map(|child| namespace_components(child, module_name, namespace, path))
+            .collect();
+        components.iter_mut().for_each(make_file_private);
+        return components;
+    }
 
+    let name = match namespace {
+        Some(namespace) => format!("{}::{}", namespace, name.join("::")),
+        None => name.join("::"),
+    };
     let mut module = ModuleComponent::new(name.clone(), path.to_string(), Cpp);
+    module.namespace = Some(name.clone());
+    let mut nested = vec![];
     ast.children
         .into_iter()
-        .flat_map(|child| find_components(child, &name, path))
+        .flat_map(|child| namespace_components(child, &name, Some(&name), path))
         .for_each(|component| match component {
             ComponentType::ClassOrInterfaceComponent(component) => {
                 match component.declaration_type {
@@ -145,15 +145,63 @@ fn transform_namespace_to_module(ast: AST, path: &str) -> Option<ModuleComponent
             ComponentType::MethodComponent(method) => {
                 module.component.methods.push(method);
             }
-            ComponentType::ModuleComponent(_module) => {
-                // unimplemented!();
+            ComponentType::ImportStmt(import) => {
+                module.imports.push(import);
+            }
+            ComponentType::MacroDefinition(definition) => {
+                module.macros.push(definition);
+            }
+            ComponentType::ModuleComponent(module) => {
+                nested.push(ComponentType::ModuleComponent(module));
             }
             _ => {
                 // unimplemented!();
             }
         });
 
-    Some(module)
+    nested.insert(0, ComponentType::ModuleComponent(module));
+    nested
+}
+
+/// Find the components declared in the body of a namespace, qualifying nested namespaces by it
+fn namespace_components(
+    ast: AST,
+    module_name: &str,
+    namespace: Option<&str>,
+    path: &str,
+) -> Vec<ComponentType> {
+    match &*ast.r#type {
+        "namespace_definition" => transform_namespace(ast, module_name, namespace, path),
+        "declaration_list" | "linkage_specification" => ast
+            .children
+            .into_iter()
+            .flat_map(|child| namespace_components(child, module_name, namespace, path))
+            .collect(),
+        _ => find_components(ast, module_name, path),
+    }
+}
+
+/// Make a component declared in an anonymous namespace private to its file
+fn make_file_private(component: &mut ComponentType) {
+    match component {
+        ComponentType::ClassOrInterfaceComponent(class) => {
+            class.component.accessor = AccessorType::File;
+        }
+        ComponentType::MethodComponent(method) => method.accessor = AccessorType::File,
+        ComponentType::ModuleComponent(module) => {
+            for class in module
+                .classes
+                .iter_mut()
+                .chain(module.interfaces.iter_mut())
+            {
+                class.component.accessor = AccessorType::File;
+            }
+            for method in module.component.methods.iter_mut() {
+                method.accessor = AccessorType::File;
+            }
+        }
+        _ => {}
+    }
 }
 
 /// Transforms an AST with type label "function_definition" or "field_declaration" or "declaration" to a `MethodComponent`
@@ -198,6 +246,21 @@ fn transform_into_method(ast: &AST, module_name: &str, path: &str) -> Option<Met
     let parameter_list = decl.find_child_by_type(&["parameter_list"])?;
     let params = func_parameters(parameter_list, module_name, path);
 
+    // `override` and `final` follow the parameters, and only apply to virtual methods
+    let virtual_specifiers: Vec<_> = decl
+        .children
+        .iter()
+        .filter(|child| child.r#type == "virtual_specifier")
+        .flat_map(|specifier| specifier.children.iter().map(|keyword| &*keyword.value))
+        .collect();
+    let is_override = virtual_specifiers.contains(&"override");
+    let is_final = virtual_specifiers.contains(&"final");
+    let is_virtual = ast
+        .find_child_by_type(&["virtual_function_specifier"])
+        .is_some()
+        || is_override
+        || is_final;
+
     let body = ast.find_child_by_type(&["compound_statement"]);
     let (line_begin, line_end) = match body {
         Some(body) => match body.span {
@@ -224,7 +287,10 @@ fn transform_into_method(ast: &AST, module_name: &str, path: &str) -> Option<Met
         parameters: params,
         is_static: false,
         is_abstract: false,
-        is_final: false,
+        is_final,
+        is_virtual,
+        is_override,
+        template: None,
         sub_methods: vec![],
         annotations: vec![],
         line_count: line_end - line_begin + 1,
@@ -270,7 +336,7 @@ fn type_ident(ast: &AST) -> String {
             let inner_types = type_args
                 .children
                 .iter()
-                .filter(|child| child.r#type == "type_descriptor")
+                .filter(|child| !matches!(&*child.r#type, "<" | "," | ">"))
                 .map(type_ident)
                 .fold(String::new(), |t1, t2| match &*t1 {
                     "" => t2,
@@ -307,13 +373,14 @@ fn func_ident(ast: &AST) -> String {
                 "constructor_name",
                 "operator_name",
                 "template_type",
+                "template_function",
             ]);
             match ident {
                 Some(ident) => func_ident(ident),
                 None => "".to_string(),
             }
         }
-        "scoped_identifier" | "template_type" => type_ident(ast),
+        "scoped_identifier" | "template_type" | "template_function" => type_ident(ast),
         "destructor_name" | "constructor_name" => {
             let ident: String = ast
                 .children
@@ -431,23 +498,75 @@ fn func_parameter(param_decl: &AST, module_name: &str, path: &str) -> Option<Met
     Some(param)
 }
 
-/// Transforms an AST with type label "class_specifier", "struct_specifier" or "type_definition" to a `ClassOrInterfaceComponent`
-fn transform_into_class(
+/// Transforms a class along with the classes nested in it, which are kept as its inner classes
+/// and named after their enclosing classes (`Outer::Inner`)
+fn transform_into_nested_class(
     ast: &AST,
+    outer: Option<&str>,
     module_name: &str,
     path: &str,
 ) -> Option<ClassOrInterfaceComponent> {
-    let class_name = ast
-        .find_child_by_type(&["type_identifier"])
-        .map_or_else(|| "".into(), |t| t.value.clone());
+    let mut class = transform_into_class(ast, module_name, path)?;
+    if let Some(outer) = outer {
+        let name = format!("{}::{}", outer, class.component.container_name);
+        class.component.component.instance_name =
+            format!("{}::{}", outer, class.component.component.instance_name);
+        class.component.container_name = name;
+    }
 
+    let outer = class.component.container_name.clone();
+    class.inner_classes = class_field_list(ast)
+        .map(|field_list| {
+            field_list
+                .children
+                .iter()
+                .flat_map(nested_class)
+                .flat_map(|nested| {
+                    transform_into_nested_class(nested, Some(&outer), module_name, path)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(class)
+}
+
+/// Get the list of members of a class
+fn class_field_list(ast: &AST) -> Option<&AST> {
     // If a "type_definition" is the given AST, it should have one of these as a child.
     let struct_specifier = ast.find_child_by_type(&["struct_specifier", "class_specifier"]);
-    let field_list = match struct_specifier {
+    match struct_specifier {
         Some(struct_specifier) => struct_specifier.find_child_by_type(&["field_declaration_list"]),
         // Both class_specifier and struct_specifier both have a field_declaration_list child
         None => ast.find_child_by_type(&["field_declaration_list"]),
-    }?;
+    }
+}
+
+/// Get the class defined by a member declaration, if it declares a nested class
+fn nested_class(field: &AST) -> Option<&AST> {
+    if field.r#type != "field_declaration" {
+        return None;
+    }
+    field
+        .find_child_by_type(&["class_specifier", "struct_specifier"])
+        .filter(|class| {
+            class
+                .find_child_by_type(&["field_declaration_list"])
+                .is_some()
+        })
+}
+
+/// Transforms an AST with type label "class_specifier", "struct_specifier" or "type_definition" to a `ClassOrInterfaceComponent`
+fn transform_into_class(
+    ast: &AST,
+    module_name: &str,
+    path: &str,
+) -> Option<ClassOrInterfaceComponent> {
+    // Specializations are named with their template arguments
+    let class_name = ast
+        .find_child_by_type(&["type_identifier", "template_type"])
+        .map_or_else(|| "".into(), type_ident);
+
+    let field_list = class_field_list(ast)?;
 
     let field_components = class_fields(&field_list.children, module_name, path);
     let mut fields = vec![];
@@ -464,13 +583,36 @@ fn transform_into_class(
     let line_end = field_list.children.last()?.span?.0;
     let line_count = line_end as i32 - line_begin as i32 + 1;
 
+    let specifier = match &*ast.r#type {
+        "type_definition" => ast.find_child_by_type(&["struct_specifier", "class_specifier"])?,
+        _ => ast,
+    };
+    let base_classes = specifier
+        .find_child_by_type(&["base_class_clause"])
+        .map_or_else(Vec::new, |clause| {
+            base_classes(clause, specifier.r#type == "struct_specifier")
+        });
+
+    let (instance_type, declaration_type) = if is_interface(&class_name, &methods, &fields) {
+        (InstanceType::InterfaceComponent, ContainerType::Interface)
+    } else {
+        (InstanceType::ClassComponent, ContainerType::Class)
+    };
+
     Some(ClassOrInterfaceComponent {
         component: ContainerComponent {
             component: ComponentInfo {
                 path: path.into(),
                 package_name: module_name.into(),
-                instance_name: format!("{}::ClassComponent", class_name),
-                instance_type: InstanceType::ClassComponent,
+                instance_name: format!(
+                    "{}::{}",
+                    class_name,
+                    match instance_type {
+                        InstanceType::InterfaceComponent => "InterfaceComponent",
+                        _ => "ClassComponent",
+                    }
+                ),
+                instance_type,
                 language: Cpp,
             },
             accessor: AccessorType::Default,
@@ -479,13 +621,50 @@ fn transform_into_class(
             container_name: class_name,
             line_count,
         },
-        declaration_type: ContainerType::Class,
+        declaration_type,
+        base_classes,
+        template: None,
         annotations: vec![],
         constructors: vec![],
         field_components: fields,
+        inner_classes: vec![],
     })
 }
 
+/// Get the classes listed by a `base_class_clause`, which are inherited privately from classes
+/// and publicly from structs unless specified
+fn base_classes(clause: &AST, is_struct: bool) -> Vec<BaseClass> {
+    let default_accessor = if is_struct {
+        AccessorType::Public
+    } else {
+        AccessorType::Private
+    };
+    let mut base_classes = vec![];
+    let mut accessor = None;
+    for child in clause.children.iter() {
+        match &*child.r#type {
+            "public" | "private" | "protected" => accessor = Some(field_accessor(child)),
+            ":" | "," | "virtual" => {}
+            _ => base_classes.push(BaseClass::new(
+                type_ident(child),
+                accessor.take().unwrap_or_else(|| default_accessor.clone()),
+            )),
+        }
+    }
+    base_classes
+}
+
+/// Whether a class only declares pure virtual methods, besides its constructors and destructor,
+/// which makes it an interface
+fn is_interface(class_name: &str, methods: &[MethodComponent], fields: &[FieldComponent]) -> bool {
+    let mut methods = methods.iter().filter(|method| {
+        method.method_name != class_name && method.method_name != format!("~{}", class_name)
+    });
+    fields.is_empty()
+        && methods.clone().next().is_some()
+        && methods.all(|method| method.is_abstract)
+}
+
 // ComponentType variants will always be FieldComponent or MethodComponent
 fn class_fields(field_list: &[AST], module_name: &str, path: &str) -> Vec<ComponentType> {
     let mut fields = vec![];
@@ -498,6 +677,24 @@ fn class_fields(field_list: &[AST], module_name: &str, path: &str) -> Vec<Compon
                     .map(field_accessor)
                     .unwrap_or(AccessorType::Default);
             }
+            // Nested classes are transformed separately
+            "field_declaration" if nested_class(field).is_some() => {}
+            // Member function templates
+            "template_declaration" => {
+                let declaration = field.find_child_by_type(&[
+                    "function_definition",
+                    "field_declaration",
+                    "declaration",
+                ]);
+                let method = declaration
+                    .and_then(|declaration| transform_into_method(declaration, module_name, path));
+                if let Some(mut method) = method {
+                    let parameters = template::template_parameters(field);
+                    method.accessor = access_specifier.clone();
+                    method.template = Some(template::template(parameters, &method.method_name));
+                    fields.push(ComponentType::MethodComponent(method));
+                }
+            }
             "function_definition" | "field_declaration" | "declaration" => {
                 // Need to consider that functions could be declared inside of the class
                 // This means I need to alter class/method merging
@@ -1022,4 +1219,132 @@ mod tests {
         let destructor = transform_into_method(&destructor, "", "").unwrap();
         assert_eq!("~CastInfoServiceIf", destructor.method_name);
     }
+
+    #[test]
+    fn inheritance_and_interfaces() {
+        let code = r#"
+class CastInfoServiceIf {
+public:
+    virtual ~CastInfoServiceIf() {}
+    virtual void WriteCastInfo(int64_t req_id, const std::string& name) = 0;
+};
+
+class CastInfoServiceHandler : public CastInfoServiceIf, Logger {
+public:
+    void WriteCastInfo(int64_t req_id, const std::string& name) override;
+    virtual void Flush() final;
+    int Count();
+};
+
+struct Entry : ns::Base<int> {};
+"#;
+        let modules = crate::test_util::parse_files(&[("cast/handler.h", code)]);
+        let module = &modules[0];
+
+        let interfaces: Vec<_> = module
+            .interfaces
+            .iter()
+            .map(|interface| &*interface.component.component.instance_name)
+            .collect();
+        assert_eq!(vec!["CastInfoServiceIf::InterfaceComponent"], interfaces);
+        assert_eq!(
+            ContainerType::Interface,
+            module.interfaces[0].declaration_type
+        );
+
+        let class = |name: &str| {
+            module
+                .classes
+                .iter()
+                .find(|class| class.component.container_name == name)
+                .unwrap()
+        };
+        let handler = class("CastInfoServiceHandler");
+        assert_eq!(
+            vec![
+                BaseClass::new("CastInfoServiceIf".into(), AccessorType::Public),
+                BaseClass::new("Logger".into(), AccessorType::Private),
+            ],
+            handler.base_classes
+        );
+        assert_eq!(
+            vec![BaseClass::new("ns::Base<int>".into(), AccessorType::Public)],
+            class("Entry").base_classes
+        );
+
+        let flags: Vec<_> = handler
+            .component
+            .methods
+            .iter()
+            .map(|m| (&*m.method_name, m.is_virtual, m.is_override, m.is_final))
+            .collect();
+        assert_eq!(
+            vec![
+                ("WriteCastInfo", true, true, false),
+                ("Flush", true, false, true),
+                ("Count", false, false, false),
+            ],
+            flags
+        );
+    }
+
+    #[test]
+    fn nested_and_anonymous_namespaces() {
+        let code = r#"
+namespace media {
+namespace cast {
+class CastHandler {
+public:
+    void Write();
+};
+}
+
+void cast::CastHandler::Write() {
+    Flush();
+}
+
+namespace {
+int Retries() { return 3; }
+}
+}
+
+namespace {
+class Cache {};
+}
+"#;
+        let modules = crate::test_util::parse_files(&[("src/cast.cpp", code)]);
+
+        let names: Vec<_> = modules
+            .iter()
+            .map(|module| (&*module.module_name, module.namespace.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("media", Some("media")),
+                ("media::cast", Some("media::cast")),
+                ("src", None)
+            ],
+            names
+        );
+
+        let media = &modules[0];
+        assert_eq!(1, media.component.methods.len());
+        let retries = &media.component.methods[0];
+        assert_eq!("Retries", retries.method_name);
+        assert_eq!(AccessorType::File, retries.accessor);
+
+        let handler = &modules[1].classes[0];
+        assert_eq!("CastHandler", handler.component.container_name);
+        assert_eq!("media::cast", handler.component.component.package_name);
+        assert!(handler.component.methods[0].body.is_some());
+
+        let cache = &modules[2].classes[0];
+        assert_eq!("Cache", cache.component.container_name);
+        assert_eq!(AccessorType::File, cache.component.accessor);
+        // Prophet only knows the usual accessors
+        assert_eq!(
+            serde_json::json!("PRIVATE"),
+            serde_json::to_value(&cache.component.accessor).unwrap()
+        );
+    }
 }
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+
+use crate::parse::AST;
+use crate::prophet::*;
+
+/// Resolves the conditional directives of a file (`#if`, `#ifdef`, `#ifndef`, `#elif`, `#else`)
+/// down to the branches kept under the defined macros, in order of appearance, so that `#define`
+/// and `#undef` directives affect the conditions that follow them. Includes are not followed.
+pub struct Preprocessor {
+    defines: BTreeMap<String, String>,
+}
+
+impl Preprocessor {
+    /// Start preprocessing a file with the given macros defined, by name, with their replacement
+    /// text
+    pub fn new(defines: &BTreeMap<String, String>) -> Preprocessor {
+        Preprocessor {
+            defines: defines.clone(),
+        }
+    }
+
+    /// Replace each conditional directive in the tree by the nodes of the branch it keeps
+    pub fn process(&mut self, mut ast: AST) -> AST {
+        ast.children = self.process_nodes(ast.children);
+        ast
+    }
+
+    fn process_nodes(&mut self, nodes: Vec<AST>) -> Vec<AST> {
+        let mut processed = vec![];
+        for node in nodes {
+            match &*node.r#type {
+                "preproc_if" | "preproc_ifdef" | "preproc_elif" | "preproc_else" => {
+                    let kept = self.select_branch(node);
+                    processed.extend(self.process_nodes(kept));
+                }
+                "preproc_def" | "preproc_function_def" => {
+                    if let Some(definition) = macro_definition(&node) {
+                        self.defines
+                            .insert(definition.name, definition.value.trim().to_string());
+                    }
+                    processed.push(node);
+                }
+                "preproc_call" => {
+                    let directive = node.find_child_by_type(&["preproc_directive"]);
+                    match directive.map(|directive| directive.value.trim()) {
+                        Some("#undef") => {
+                            if let Some(name) = node.find_child_by_type(&["preproc_arg"]) {
+                                self.defines.remove(name.value.trim());
+                            }
+                        }
+                        _ => processed.push(node),
+                    }
+                }
+                _ => processed.push(self.process(node)),
+            }
+        }
+        processed
+    }
+
+    /// Retrieve the nodes of the branch kept by a conditional directive, following its
+    /// `#elif`/`#else` alternatives if its condition does not hold
+    fn select_branch(&self, node: AST) -> Vec<AST> {
+        let holds = match &*node.r#type {
+            "preproc_else" => true,
+            "preproc_ifdef" => {
+                let defined = node
+                    .children
+                    .get(1)
+                    .is_some_and(|name| self.defines.contains_key(&name.value));
+                match node.children.first().map(|directive| &*directive.value) {
+                    Some("#ifndef") => !defined,
+                    _ => defined,
+                }
+            }
+            _ => match node
+                .children
+                .get(1)
+                .map(|condition| self.evaluate(condition))
+            {
+                Some(Some(value)) => value != 0,
+                _ => {
+                    tracing::warn!("Could not evaluate preprocessor condition, assuming false");
+                    false
+                }
+            },
+        };
+
+        // The condition (or the macro name) follows the directive, except for #else
+        let skip = if node.r#type == "preproc_else" { 1 } else { 2 };
+        let mut alternative = None;
+        let mut body = vec![];
+        for child in node.children.into_iter().skip(skip) {
+            match &*child.r#type {
+                "preproc_elif" | "preproc_else" => alternative = Some(child),
+                "#endif" | "\n" => {}
+                _ => body.push(child),
+            }
+        }
+
+        if holds {
+            body
+        } else {
+            alternative.map_or_else(Vec::new, |alternative| self.select_branch(alternative))
+        }
+    }
+
+    /// Evaluate the integer value of a condition, if it is made of supported expressions
+    fn evaluate(&self, condition: &AST) -> Option<i64> {
+        match &*condition.r#type {
+            "number_literal" => parse_integer(&condition.value),
+            "true" => Some(1),
+            "false" => Some(0),
+            "identifier" => Some(self.evaluate_macro(&condition.value, 0)),
+            "preproc_defined" => {
+                let name = condition.find_child_by_type(&["identifier"])?;
+                Some(self.defines.contains_key(&name.value) as i64)
+            }
+            "parenthesized_expression" => self.evaluate(condition.children.get(1)?),
+            "unary_expression" => {
+                let operand = self.evaluate(condition.children.get(1)?)?;
+                match &*condition.children.first()?.value {
+                    "!" => Some((operand == 0) as i64),
+                    "-" => operand.checked_neg(),
+                    "+" => Some(operand),
+                    "~" => Some(!operand),
+                    _ => None,
+                }
+            }
+            "binary_expression" => {
+                let lhs = self.evaluate(condition.children.first()?)?;
+                let op = &*condition.children.get(1)?.value;
+                // Short-circuit, so the unevaluated side may be unsupported
+                match (op, lhs) {
+                    ("&&", 0) => return Some(0),
+                    ("||", lhs) if lhs != 0 => return Some(1),
+                    _ => {}
+                }
+                let rhs = self.evaluate(condition.children.get(2)?)?;
+                Some(match op {
+                    "&&" | "||" => (rhs != 0) as i64,
+                    "==" => (lhs == rhs) as i64,
+                    "!=" => (lhs != rhs) as i64,
+                    "<" => (lhs < rhs) as i64,
+                    "<=" => (lhs <= rhs) as i64,
+                    ">" => (lhs > rhs) as i64,
+                    ">=" => (lhs >= rhs) as i64,
+                    "+" => lhs.wrapping_add(rhs),
+                    "-" => lhs.wrapping_sub(rhs),
+                    "*" => lhs.wrapping_mul(rhs),
+                    "/" => lhs.checked_div(rhs)?,
+                    "%" => lhs.checked_rem(rhs)?,
+                    "&" => lhs & rhs,
+                    "|" => lhs | rhs,
+                    "^" => lhs ^ rhs,
+                    "<<" => lhs.checked_shl(rhs as u32)?,
+                    ">>" => lhs.checked_shr(rhs as u32)?,
+                    _ => return None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluate a macro used in a condition: its value if it expands to a number or to another
+    /// macro, and 0 otherwise (as for undefined macros)
+    fn evaluate_macro(&self, name: &str, depth: usize) -> i64 {
+        let value = match self.defines.get(name) {
+            Some(value) if depth < 16 => value,
+            _ => return 0,
+        };
+        match parse_integer(value) {
+            Some(value) => value,
+            None if is_identifier(value) => self.evaluate_macro(value, depth + 1),
+            None => 0,
+        }
+    }
+}
+
+/// Lower a `#define` directive into the macro it defines
+pub fn macro_definition(ast: &AST) -> Option<MacroDefinition> {
+    let name = ast.find_child_by_type(&["identifier"])?.value.clone();
+    let parameters = ast.find_child_by_type(&["preproc_params"]).map(|params| {
+        params
+            .children
+            .iter()
+            .filter(|param| !matches!(&*param.r#type, "(" | ")" | ","))
+            .map(|param| param.value.clone())
+            .collect()
+    });
+    let value = ast
+        .find_child_by_type(&["preproc_arg"])
+        .map_or_else(String::new, |value| value.value.trim().to_string());
+    Some(MacroDefinition::new(name, parameters, value))
+}
+
+/// Parse a C++ integer literal, ignoring digit separators and suffixes
+fn parse_integer(literal: &str) -> Option<i64> {
+    let literal = literal
+        .trim()
+        .trim_end_matches(['u', 'U', 'l', 'L'])
+        .replace('\'', "");
+    let (digits, radix) = match literal.get(..2) {
+        Some("0x") | Some("0X") => (&literal[2..], 16),
+        Some("0b") | Some("0B") => (&literal[2..], 2),
+        _ if literal.len() > 1 && literal.starts_with('0') => (&literal[1..], 8),
+        _ => (&*literal, 10),
+    };
+    i64::from_str_radix(digits, radix).ok()
+}
+
+fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{convert_trees_to_laast_with_options, parse_ast, ParseOptions, ParsedTree};
+    use rust_code_analysis::AstPayload;
+
+    #[test]
+    fn keeps_branches_of_defined_macros() {
+        let code = r#"
+#include "utils.h"
+#include <vector>
+#define DEBUG
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define SIZE 10
+#undef SIZE
+
+#ifdef DEBUG
+int debug() { return 1; }
+#elif defined(TRACE)
+int trace() { return 2; }
+#else
+int release() { return 0; }
+#endif
+
+#if defined(SIZE) || LEVEL > 1
+int verbose() { return 3; }
+#endif
+
+#ifndef DEBUG
+int quiet() { return 4; }
+#endif
+        "#;
+        let (ast, lang) = parse_ast(AstPayload {
+            id: "".to_owned(),
+            file_name: "service.cpp".to_owned(),
+            code: code.to_owned(),
+            comment: false,
+            span: true,
+        })
+        .unwrap();
+        let trees = vec![ParsedTree::new(
+            ast,
+            lang,
+            "service".into(),
+            "service".into(),
+        )];
+
+        let function_names = |level: &str| {
+            let options = ParseOptions {
+                defines: [("LEVEL".to_string(), level.to_string())].into(),
+            };
+            let modules = convert_trees_to_laast_with_options(trees.clone(), &options).unwrap();
+            let module = &modules[0];
+            let imports: Vec<_> = module.imports.iter().map(|i| i.value.as_str()).collect();
+            assert_eq!(vec!["utils.h", "vector"], imports);
+            let macros: Vec<_> = module
+                .macros
+                .iter()
+                .map(|m| (m.name.as_str(), m.parameters.clone(), m.value.as_str()))
+                .collect();
+            assert_eq!(
+                vec![
+                    ("DEBUG", None, ""),
+                    (
+                        "MAX",
+                        Some(vec!["a".to_string(), "b".to_string()]),
+                        "((a) > (b) ? (a) : (b))"
+                    ),
+                    ("SIZE", None, "10"),
+                ],
+                macros
+            );
+            module
+                .component
+                .methods
+                .iter()
+                .map(|method| method.method_name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["debug"], function_names("1"));
+        assert_eq!(vec!["debug", "verbose"], function_names("2"));
+    }
+}
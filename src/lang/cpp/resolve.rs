@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::prophet::*;
+
+/// Matches the namespace or class qualifiers of a type (`std::`, `ns::Foo::`)
+static QUALIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\w+::").unwrap());
+static CONST: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bconst\b").unwrap());
+
+/// The module of a class, whether it is one of the module's interfaces, and its index, followed
+/// by its index among the inner classes of each enclosing class
+type Location = (usize, bool, Vec<usize>);
+
+/// Pairs the out-of-line definitions of class methods (`void ns::Foo::bar(int) { ... }`), which
+/// are lowered to module-level functions, with the methods declared by their classes, across
+/// modules. Owners are looked up from the namespace of the definition outwards; overloads are told
+/// apart by their parameter types. Definitions whose class is not part of the project, or cannot
+/// be told apart from others, are left in their module: those of classes whose qualified name is
+/// declared more than once, and those matching several overloads with as many parameters but no
+/// declaration exactly. Class template specializations are linked to their primary template by
+/// its qualified name.
+pub fn resolve_definitions(modules: &mut [ModuleComponent]) {
+    // Classes declared more than once have no location
+    let mut classes = BTreeMap::new();
+    for (i, module) in modules.iter().enumerate() {
+        let namespace = module.namespace.as_deref();
+        for (j, class) in module.classes.iter().enumerate() {
+            add_class(&mut classes, namespace, class, (i, false, vec![j]));
+        }
+        for (j, interface) in module.interfaces.iter().enumerate() {
+            add_class(&mut classes, namespace, interface, (i, true, vec![j]));
+        }
+    }
+
+    let mut primaries = vec![];
+    for (name, location) in classes.iter() {
+        let location = match location {
+            Some(location) => location,
+            None => continue,
+        };
+        let (module, class) = get(modules, location);
+        let primary = class.template.as_ref().and_then(|t| t.primary.as_deref());
+        if let Some(primary) = primary {
+            match find_owner(&classes, module.namespace.as_deref(), primary) {
+                Some((primary, Some(_))) => primaries.push((location.clone(), primary.clone())),
+                _ => tracing::debug!("No primary template found for {}", name),
+            }
+        }
+    }
+    for (location, primary) in primaries {
+        if let Some(template) = get_mut(modules, &location).template.as_mut() {
+            template.primary = Some(primary);
+        }
+    }
+
+    for i in 0..modules.len() {
+        let namespace = modules[i].namespace.clone();
+        let functions = std::mem::take(&mut modules[i].component.methods);
+        let mut unresolved = vec![];
+        for function in functions {
+            let owner = split_qualified_name(&function.method_name).and_then(|(owner, name)| {
+                let (owner, location) =
+                    find_owner(&classes, namespace.as_deref(), owner).or_else(|| {
+                        let owner = strip_template_arguments(owner);
+                        find_owner(&classes, namespace.as_deref(), &owner)
+                    })?;
+                if location.is_none() {
+                    tracing::debug!("Class {} is declared more than once", owner);
+                }
+                Some((location?, name.to_string()))
+            });
+            let unattached = match owner {
+                Some((location, name)) => {
+                    attach_definition(get_mut(modules, &location), function, name)
+                }
+                None => Some(function),
+            };
+            unresolved.extend(unattached);
+        }
+        modules[i].component.methods = unresolved;
+    }
+
+    // Link the specializations of functions to their primary template, like those of classes
+    let mut templates = BTreeMap::new();
+    for module in modules.iter() {
+        for function in module.component.methods.iter() {
+            if matches!(&function.template, Some(template) if template.primary.is_none()) {
+                let name = qualify(module.namespace.as_deref(), &function.method_name);
+                templates.insert(name, ());
+            }
+        }
+    }
+    for module in modules.iter_mut() {
+        let namespace = module.namespace.as_deref();
+        for function in module.component.methods.iter_mut() {
+            let template = match function.template.as_mut() {
+                Some(template) => template,
+                None => continue,
+            };
+            if let Some(primary) = template.primary.as_deref() {
+                match find_owner(&templates, namespace, primary) {
+                    Some((primary, _)) => template.primary = Some(primary.clone()),
+                    None => {
+                        tracing::debug!("No primary template found for {}", function.method_name)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Record the location of a class and of the classes nested in it, or that their qualified name
+/// is declared more than once
+fn add_class(
+    classes: &mut BTreeMap<String, Option<Location>>,
+    namespace: Option<&str>,
+    class: &ClassOrInterfaceComponent,
+    location: Location,
+) {
+    for (k, inner_class) in class.inner_classes.iter().enumerate() {
+        let (m, interface, mut path) = location.clone();
+        path.push(k);
+        add_class(classes, namespace, inner_class, (m, interface, path));
+    }
+    let name = qualify(namespace, &class.component.container_name);
+    classes
+        .entry(name)
+        .and_modify(|location| *location = None)
+        .or_insert(Some(location));
+}
+
+fn get<'a>(
+    modules: &'a [ModuleComponent],
+    (m, interface, path): &Location,
+) -> (&'a ModuleComponent, &'a ClassOrInterfaceComponent) {
+    let module = &modules[*m];
+    let classes = match interface {
+        false => &module.classes,
+        true => &module.interfaces,
+    };
+    let mut class = &classes[path[0]];
+    for &k in path[1..].iter() {
+        class = &class.inner_classes[k];
+    }
+    (module, class)
+}
+
+fn get_mut<'a>(
+    modules: &'a mut [ModuleComponent],
+    (m, interface, path): &Location,
+) -> &'a mut ClassOrInterfaceComponent {
+    let module = &mut modules[*m];
+    let classes = match interface {
+        false => &mut module.classes,
+        true => &mut module.interfaces,
+    };
+    let mut class = &mut classes[path[0]];
+    for &k in path[1..].iter() {
+        class = &mut class.inner_classes[k];
+    }
+    class
+}
+
+fn qualify(namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(namespace) if !namespace.is_empty() => format!("{}::{}", namespace, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Split a qualified function name into its owner and its own name (`Foo<T>::bar` gives `Foo<T>`
+/// and `bar`)
+fn split_qualified_name(name: &str) -> Option<(&str, &str)> {
+    // Operators such as `operator<` may hold angle brackets and colons
+    let operator = name.find("operator").unwrap_or(name.len());
+    let mut depth = 0;
+    let mut split = None;
+    for (i, c) in name[..operator].char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ':' if depth == 0 && name[i..].starts_with("::") => split = Some(i),
+            _ => {}
+        }
+    }
+    let split = split?;
+    Some((&name[..split], &name[split + 2..]))
+}
+
+/// Remove the template arguments from a name (`ns::Foo<T>` gives `ns::Foo`)
+fn strip_template_arguments(name: &str) -> String {
+    let mut depth = 0;
+    name.chars()
+        .filter(|c| {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => return depth == 0,
+            }
+            false
+        })
+        .collect()
+}
+
+/// Find the class (or function) with the given name from a namespace, trying the enclosing
+/// namespaces from the innermost to the global one, then any single one whose qualified name ends
+/// with the name (as brought in by `using namespace`)
+fn find_owner<'a, T: Clone>(
+    classes: &'a BTreeMap<String, T>,
+    namespace: Option<&str>,
+    owner: &str,
+) -> Option<(&'a String, T)> {
+    let owner = owner.trim_start_matches("::");
+    let mut scope = namespace.unwrap_or_default();
+    loop {
+        if let Some((name, class)) = classes.get_key_value(&qualify(Some(scope), owner)) {
+            return Some((name, class.clone()));
+        }
+        if scope.is_empty() {
+            break;
+        }
+        scope = scope.rsplit_once("::").map_or("", |(outer, _)| outer);
+    }
+
+    let suffix = format!("::{}", owner);
+    let mut candidates = classes
+        .iter()
+        .filter(|(name, _)| name.ends_with(&suffix))
+        .map(|(name, class)| (name, class.clone()));
+    match (candidates.next(), candidates.next()) {
+        (Some(class), None) => Some(class),
+        _ => None,
+    }
+}
+
+/// Give a declared method the body of its definition, or add the definition to the class if it
+/// was not declared. When the parameter types match no declaration, the only declaration with as
+/// many parameters is taken; if there are several, the definition is given back unattached.
+fn attach_definition(
+    class: &mut ClassOrInterfaceComponent,
+    mut definition: MethodComponent,
+    name: String,
+) -> Option<MethodComponent> {
+    let methods = &mut class.component.methods;
+    let parameters = parameter_types(&definition);
+    let mut declared = methods
+        .iter()
+        .position(|m| m.method_name == name && parameter_types(m) == parameters);
+    if declared.is_none() {
+        let mut overloads = methods.iter().enumerate().filter(|(_, m)| {
+            m.method_name == name && m.parameters.len() == definition.parameters.len()
+        });
+        match (overloads.next(), overloads.next()) {
+            (Some((i, _)), None) => declared = Some(i),
+            (Some(_), Some(_)) => {
+                tracing::debug!("Definition of {} matches several overloads", name);
+                return Some(definition);
+            }
+            _ => {}
+        }
+    }
+
+    match declared {
+        Some(i) => {
+            let method = &mut methods[i];
+            method.component.path = definition.component.path;
+            method.line_begin = definition.line_begin;
+            method.line_end = definition.line_end;
+            method.line_count = definition.line_count;
+            method.parameters = definition.parameters;
+            method.body = definition.body;
+        }
+        None => {
+            definition.method_name = name;
+            methods.push(definition);
+        }
+    }
+    None
+}
+
+/// The parameter types of a method, without qualifiers, `const` or spacing
+fn parameter_types(method: &MethodComponent) -> Vec<String> {
+    method
+        .parameters
+        .iter()
+        .map(|param| {
+            let unqualified = QUALIFIER.replace_all(&param.r#type, "");
+            CONST
+                .replace_all(&unqualified, "")
+                .split_whitespace()
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_qualified_name, strip_template_arguments};
+    use crate::test_util::parse_files;
+
+    #[test]
+    fn splits_qualified_names() {
+        let split = split_qualified_name;
+        assert_eq!(Some(("Foo<T>", "bar")), split("Foo<T>::bar"));
+        assert_eq!(Some(("A<B::C>", "f")), split("A<B::C>::f"));
+        assert_eq!(Some(("ns::Foo", "~Foo")), split("ns::Foo::~Foo"));
+        assert_eq!(Some(("Foo", "operator<")), split("Foo::operator<"));
+        assert_eq!(None, split("main"));
+        assert_eq!("ns::Foo", strip_template_arguments("ns::Foo<Bar<T>>"));
+    }
+
+    #[test]
+    fn pairs_definitions_with_declarations() {
+        let header = r#"
+namespace shop {
+class Processor {
+public:
+    int handle(int count);
+    int handle(const std::string& name);
+    void log(int level);
+    void log(double level);
+    struct Entry {
+        void touch();
+    };
+};
+
+class ProcessorFactory {
+public:
+    Processor create();
+};
+
+template <typename T>
+class Box {
+public:
+    T get();
+};
+}
+"#;
+        let source = r#"
+#include "processor.h"
+
+namespace shop {
+int Processor::handle(int count) {
+    return count;
+}
+
+int Processor::handle(const std::string& name) {
+    return name.size();
+}
+
+void Processor::Entry::touch() {
+    stamp();
+}
+
+template <typename T>
+T Box<T>::get() {
+    return value;
+}
+}
+
+shop::Processor shop::ProcessorFactory::create() {
+    return Processor();
+}
+
+void shop::Processor::log(float level) {}
+
+void shop::Ledger::post() {}
+
+int main() {
+    return 0;
+}
+"#;
+        // Two classes share the qualified name `shop::Ledger`, so neither gets the definition
+        let ledger = r#"
+namespace shop {
+class Ledger {
+public:
+    void post();
+};
+}
+"#;
+        let modules = parse_files(&[
+            ("src/processor.h", header),
+            ("src/processor.cpp", source),
+            ("src/ledger.h", ledger),
+            ("legacy/ledger.h", ledger),
+        ]);
+
+        let mut functions: Vec<_> = modules
+            .iter()
+            .flat_map(|module| module.component.methods.iter())
+            .map(|method| method.method_name.as_str())
+            .collect();
+        functions.sort_unstable();
+        assert_eq!(
+            vec!["main", "shop::Ledger::post", "shop::Processor::log"],
+            functions
+        );
+
+        let shop = modules
+            .iter()
+            .find(|module| module.namespace.as_deref() == Some("shop"))
+            .unwrap();
+        let class = |name: &str| {
+            shop.classes
+                .iter()
+                .flat_map(|class| std::iter::once(class).chain(class.inner_classes.iter()))
+                .find(|class| class.component.container_name == name)
+                .unwrap()
+        };
+        let bodies = |name: &str| {
+            class(name)
+                .component
+                .methods
+                .iter()
+                .map(|method| {
+                    (
+                        method.method_name.as_str(),
+                        method.line_begin,
+                        method.body.is_some(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                ("handle", 5, true),
+                ("handle", 9, true),
+                ("log", 0, false),
+                ("log", 0, false)
+            ],
+            bodies("Processor")
+        );
+        assert_eq!(vec![("touch", 13, true)], bodies("Processor::Entry"));
+        let inner_classes: Vec<_> = class("Processor")
+            .inner_classes
+            .iter()
+            .map(|class| class.component.container_name.as_str())
+            .collect();
+        assert_eq!(vec!["Processor::Entry"], inner_classes);
+        assert!(!shop
+            .classes
+            .iter()
+            .any(|class| class.component.container_name == "Processor::Entry"));
+        assert_eq!(vec![("create", 23, true)], bodies("ProcessorFactory"));
+        assert_eq!(vec![("get", 18, true)], bodies("Box"));
+        let handle = &class("Processor").component.methods[1];
+        assert_eq!("name", handle.parameters[0].parameter_name);
+        assert_eq!("src/processor.cpp", handle.component.path);
+    }
+}
@@ -3,9 +3,30 @@ use crate::ast::*;
 use crate::parse::AST;
 use Language::Cpp;
 
+/// Takes child of compound_statement and turns it into its Nodes. A labeled statement gives its
+/// label followed by the statement it labels, as siblings in the enclosing block.
+pub fn body_nodes(node: &AST) -> Vec<Node> {
+    match &*node.r#type {
+        "labeled_statement" => {
+            let label = match node.find_child_by_type(&["statement_identifier"]) {
+                Some(label) => label,
+                None => return vec![],
+            };
+            let label: Stmt = LabelStmt::new(label.value.clone(), Cpp).into();
+            let statement = node.children.last().into_iter().flat_map(body_nodes);
+            std::iter::once(label.into()).chain(statement).collect()
+        }
+        _ => body_node(node).into_iter().collect(),
+    }
+}
+
 /// Takes child of compound_statement and turns it into a Node
 pub fn body_node(node: &AST) -> Option<Node> {
     match &*node.r#type {
+        "declaration" | "expression_statement" if is_coroutine_statement(node) => {
+            let stmt: Stmt = coroutine_statement(node)?;
+            Some(stmt.into())
+        }
         "declaration" => {
             let decl: Stmt = variable_declaration(node).into();
             Some(decl.into())
@@ -14,6 +35,10 @@ pub fn body_node(node: &AST) -> Option<Node> {
             let while_stmt: Stmt = while_statement(node)?.into();
             Some(while_stmt.into())
         }
+        "do_statement" => {
+            let do_while_stmt: Stmt = do_while_statement(node)?.into();
+            Some(do_while_stmt.into())
+        }
         "for_statement" => {
             let for_stmt: Stmt = for_statement(node)?.into();
             Some(for_stmt.into())
@@ -46,10 +71,15 @@ pub fn body_node(node: &AST) -> Option<Node> {
             let brk: Stmt = BreakStmt::new(Cpp).into();
             Some(brk.into())
         }
-        "continue_statment" => {
+        "continue_statement" => {
             let cont: Stmt = ContinueStmt::new(Cpp).into();
             Some(cont.into())
         }
+        "goto_statement" => {
+            let label = node.find_child_by_type(&["statement_identifier"])?;
+            let goto: Stmt = GotoStmt::new(label.value.clone(), Cpp).into();
+            Some(goto.into())
+        }
         "throw_statement" => {
             let throw: Stmt = throw_statement(node).into();
             Some(throw.into())
@@ -163,6 +193,54 @@ fn variable_init_declaration(init_declarator: &AST, mut variable_type: String) -
     DeclStmt::new(vec![var_decl], rhs, Cpp)
 }
 
+/// Lower the body of a control statement, which may be a single statement rather than a block
+fn statement_block(body: &AST) -> Block {
+    match &*body.r#type {
+        "compound_statement" => func_body(body),
+        _ => Block::new(body_nodes(body), Cpp),
+    }
+}
+
+/// The grammar does not support coroutines: `co_await g(x);` is parsed as a declaration of `g`
+/// with the type `co_await`, and `co_return;` as an expression statement of an identifier
+fn is_coroutine_statement(node: &AST) -> bool {
+    node.children.first().is_some_and(|keyword| {
+        matches!(&*keyword.r#type, "type_identifier" | "identifier")
+            && matches!(&*keyword.value, "co_await" | "co_yield" | "co_return")
+    })
+}
+
+/// Recover a `co_return` into a return, and a `co_await` or `co_yield` into a unary expression
+fn coroutine_statement(node: &AST) -> Option<Stmt> {
+    let keyword = &*node.children.first()?.value;
+    let operand = node.children.get(1).and_then(coroutine_operand);
+    Some(match keyword {
+        "co_return" => ReturnStmt::new(operand, Cpp).into(),
+        _ => {
+            let op = Op::Other(keyword.to_string());
+            let expr: Expr = UnaryExpr::new(Box::new(operand?), op, Cpp).into();
+            expr.into()
+        }
+    })
+}
+
+fn coroutine_operand(node: &AST) -> Option<Expr> {
+    match &*node.r#type {
+        // `g(x)` is parsed as a declarator of `g` initialized with `(x)`
+        "init_declarator" => {
+            let callee = expression(node.children.first()?)?;
+            let args = node
+                .find_child_by_type(&["argument_list"])?
+                .children
+                .iter()
+                .filter_map(expression)
+                .collect();
+            Some(CallExpr::new(Box::new(callee), args, Cpp).into())
+        }
+        _ => expression(node),
+    }
+}
+
 fn expression_statement(node: &AST) -> Option<Stmt> {
     let expr = node.children.first().map_or_else(|| None, expression)?;
     Some(expr.into())
@@ -175,6 +253,14 @@ fn import_statement(using_declaration: &AST) -> Option<ImportStmt> {
     Some(ImportStmt::new(false, false, using, Cpp))
 }
 
+/// Lower an `#include` into an import of the included file
+pub fn include_statement(include: &AST) -> Option<ImportStmt> {
+    let file =
+        include.find_child_by_type(&["string_literal", "system_lib_string", "identifier"])?;
+    let file = file.value.trim_matches(|c| matches!(c, '"' | '<' | '>'));
+    Some(ImportStmt::new(false, true, file.to_string(), Cpp))
+}
+
 fn return_statement(return_stmt: &AST) -> ReturnStmt {
     // If there isn't an expression and the 2nd child is of type ";",
     // the expression function will return None anyways.
@@ -200,37 +286,22 @@ fn try_catch_statement(try_catch_stmt: &AST) -> Option<TryCatchStmt> {
 }
 
 fn if_statement(if_stmt: &AST) -> Option<IfStmt> {
-    let cond = if_stmt
-        .find_child_by_type(&["condition_clause"])
+    let mut children = if_stmt.children.iter();
+    let cond = children
+        .find(|node| &*node.r#type == "condition_clause")
         .map(expression)??;
-    let mut blocks = if_stmt
-        .children
-        .iter()
-        .filter(|node| &*node.r#type == "compound_statement")
-        .map(|block| Block::new(block_nodes(block), Cpp));
-    let body = match blocks.next() {
-        Some(block) => block,
-        None => {
-            let stmt = if_stmt.children.iter().last()?;
-            let stmt = body_node(stmt)?;
-            Block::new(vec![stmt], Cpp)
-        }
-    };
+    let body = statement_block(children.next()?);
     // Check for else block, if else block, or no else block.
-    let else_body = match blocks.next() {
-        Some(else_body) => Some(else_body),
-        None => {
-            let else_if = if_stmt
-                .find_child_by_type(&["if_statement"])
-                .map(if_statement)
-                .flatten()
-                .map(|if_stmt| {
-                    let if_stmt: Stmt = if_stmt.into();
-                    Block::new(vec![if_stmt.into()], Cpp)
-                });
-            else_if
-        }
-    };
+    let else_body = children
+        .find(|node| &*node.r#type == "else")
+        .and_then(|_| children.next())
+        .map(|else_body| match &*else_body.r#type {
+            "if_statement" => {
+                let else_if = if_statement(else_body).map(|if_stmt| Stmt::from(if_stmt).into());
+                Block::new(else_if.into_iter().collect(), Cpp)
+            }
+            _ => statement_block(else_body),
+        });
     Some(IfStmt::new(cond, body, else_body, Cpp))
 }
 
@@ -241,6 +312,7 @@ fn switch_statement(switch_stmt: &AST) -> Option<SwitchExpr> {
     let cases = switch_stmt
         .find_child_by_type(&["compound_statement"])
         .map(|switch_stmt| switch_stmt.children.iter())?
+        .filter(|case| case.r#type == "case_statement")
         .flat_map(switch_case)
         .collect();
 
@@ -256,11 +328,19 @@ fn switch_case(case_statement: &AST) -> Option<CaseExpr> {
         // "default"
         _ => None,
     };
-    if case_statement.children.len() < 4 {
-        tracing::info!("Malformed case statement {:#?}", case_statement);
-        return None;
-    }
-    let nodes = block_nodes_iter(&case_statement.children[3..]);
+    // Cases without statements fall through to the next one, and are kept with an empty body
+    let colon = match case_statement
+        .children
+        .iter()
+        .position(|child| child.r#type == ":")
+    {
+        Some(colon) => colon,
+        None => {
+            tracing::info!("Malformed case statement {:#?}", case_statement);
+            return None;
+        }
+    };
+    let nodes = block_nodes_iter(&case_statement.children[colon + 1..]);
     let block = Block::new(nodes, Cpp);
     let case = CaseExpr::new(expr.map(Box::new), Box::new(block), Cpp);
     Some(case)
@@ -270,24 +350,31 @@ fn while_statement(while_stmt: &AST) -> Option<WhileStmt> {
     let cond = while_stmt
         .find_child_by_type(&["condition_clause"])
         .map(expression)??;
-    let nodes = while_stmt
-        .find_child_by_type(&["compound_statement"])
-        .map(block_nodes)?;
-    Some(WhileStmt::new(cond, Block::new(nodes, Cpp), Cpp))
+    let body = statement_block(while_stmt.children.last()?);
+    Some(WhileStmt::new(cond, body, Cpp))
+}
+
+fn do_while_statement(do_stmt: &AST) -> Option<DoWhileStmt> {
+    let body = statement_block(do_stmt.children.get(1)?);
+    let cond = do_stmt
+        .find_child_by_type(&["parenthesized_expression"])?
+        .children
+        .get(1)
+        .and_then(expression)?;
+    Some(DoWhileStmt::new(cond, body, Cpp))
 }
 
 fn for_statement(for_stmt: &AST) -> Option<ForStmt> {
-    let block = for_stmt.find_child_by_type(&["compound_statement"])?;
-    let block = func_body(block);
+    let (body, header) = for_stmt.children.split_last()?;
+    let block = statement_block(body);
 
     let mut init = None;
     let mut cond = None;
     let mut post = None;
     let mut semicolons = 0u8;
-    for part in for_stmt
-        .children
+    for part in header
         .iter()
-        .filter(|child| !matches!(&*child.r#type, "for" | "(" | ")" | "compound_statement"))
+        .filter(|child| !matches!(&*child.r#type, "for" | "(" | ")"))
     {
         if &*part.r#type == ";" {
             semicolons += 1;
@@ -320,59 +407,59 @@ fn for_statement(for_stmt: &AST) -> Option<ForStmt> {
 }
 
 fn for_range_statement(for_range_loop: &AST) -> Option<ForRangeStmt> {
-    let block = for_range_loop.find_child_by_type(&["compound_statement"])?;
-    let block = func_body(block);
+    let (body, header) = for_range_loop.children.split_last()?;
+    let block = statement_block(body);
 
-    let mut r#type = None;
-    let mut decl = None;
-    let mut iterator = None;
-    for (i, part) in for_range_loop
-        .children
+    // The declaration of the loop variable comes before the colon, the range after it
+    let colon = header.iter().position(|child| child.r#type == ":")?;
+    let (declaration, range) = header.split_at(colon);
+    let iterator = range.get(1).and_then(expression);
+
+    // Qualifiers (`const auto&`) are kept where they are written, around the type
+    let mut r#type = declaration
         .iter()
-        .filter(|child| {
-            !matches!(
-                &*child.r#type,
-                "for" | "(" | ":" | ")" | "compound_statement"
-            )
+        .filter_map(|part| match &*part.r#type {
+            "auto"
+            | "primitive_type"
+            | "type_identifier"
+            | "scoped_type_identifier"
+            | "template_type" => Some(type_ident(part)),
+            "type_qualifier" => Some(
+                part.children
+                    .first()
+                    .map_or_else(|| part.value.clone(), |keyword| keyword.value.clone()),
+            ),
+            _ => None,
         })
-        .enumerate()
-    {
-        match i {
-            // Declarations need to be considered for the initialization. Regular BinExpr
-            // are treated as ExprStmt here.
-            0 => r#type = expression(part),
-            1 => decl = expression(part),
-            2 => iterator = expression(part),
-            _ => {}
-        }
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // Structured bindings (`auto& [key, value]`) declare a variable per name
+    let mut declarator = declaration.last()?;
+    while matches!(
+        &*declarator.r#type,
+        "reference_declarator" | "pointer_declarator"
+    ) {
+        let (inner, symbols) = declarator.children.split_last()?;
+        symbols
+            .iter()
+            .for_each(|symbol| r#type.push_str(&symbol.value));
+        declarator = inner;
     }
-
-    // Convert generic node to statement
-    let mut r#type = match r#type {
-        Some(Expr::Literal(t)) => t.value,
-        Some(Expr::Ident(ident)) => ident.name,
-        _ => "".into(),
-    };
-    let mut is_unary = true;
-    while is_unary {
-        match decl {
-            Some(Expr::UnaryExpr(expr)) => {
-                r#type.push_str(match expr.op {
-                    Op::Star => "*",
-                    Op::And => "&",
-                    _ => "",
-                });
-                decl = Some(*expr.expr);
-                is_unary = true;
-            }
-            _ => is_unary = false,
-        }
-    }
-    // let decl = DeclStmt::new(Some(r#type), vec![decl?]);
-    let variables = match decl {
-        Some(Expr::Ident(ident)) => vec![VarDecl::new(Some(r#type), ident, Cpp)],
-        _ => vec![],
+    let names = match &*declarator.r#type {
+        "structured_binding_declarator" => declarator
+            .children
+            .iter()
+            .filter(|child| child.r#type == "identifier")
+            .map(|name| name.value.clone())
+            .collect(),
+        _ => vec![variable_ident_inner(declarator, &mut r#type)?],
     };
+
+    let variables = names
+        .into_iter()
+        .map(|name| VarDecl::new(Some(r#type.clone()), Ident::new(name, Cpp), Cpp))
+        .collect();
     let decl = DeclStmt::new(variables, vec![], Cpp);
     let for_range_stmt = ForRangeStmt::new(Box::new(decl.into()), iterator, block, Cpp);
     Some(for_range_stmt)
@@ -399,6 +486,33 @@ fn catch_statement(catch_clause: &AST) -> Option<CatchStmt> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse_ast;
+    use rust_code_analysis::AstPayload;
+
+    /// Lower the statements of a function body
+    fn lower_body(body: &str) -> Vec<Node> {
+        let (ast, _) = parse_ast(AstPayload {
+            id: "".to_owned(),
+            file_name: "body.cpp".to_owned(),
+            code: format!("void run() {{\n{}\n}}", body),
+            comment: false,
+            span: true,
+        })
+        .unwrap();
+        let function = ast.find_child_by_type(&["function_definition"]).unwrap();
+        block_nodes(
+            function
+                .find_child_by_type(&["compound_statement"])
+                .unwrap(),
+        )
+    }
+
+    fn stmt(node: &Node) -> &Stmt {
+        match node {
+            Node::Stmt(stmt) => stmt,
+            node => panic!("Not a statement: {:#?}", node),
+        }
+    }
 
     #[test]
     fn return_stmt_test() {
@@ -750,4 +864,142 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn loops_jumps_and_labels() {
+        let nodes = lower_body(
+            r#"
+for (const auto& entry : entries) continue;
+do {
+    i++;
+} while (i < 3);
+retry:
+    if (failed) goto retry; else if (done) return; else log();
+"#,
+        );
+        assert_eq!(4, nodes.len());
+
+        let expected: Stmt = ForRangeStmt::new(
+            Box::new(
+                DeclStmt::new(
+                    vec![VarDecl::new(
+                        Some("const auto&".into()),
+                        Ident::new("entry".into(), Cpp),
+                        Cpp,
+                    )],
+                    vec![],
+                    Cpp,
+                )
+                .into(),
+            ),
+            Some(Ident::new("entries".into(), Cpp).into()),
+            Block::new(vec![Stmt::from(ContinueStmt::new(Cpp)).into()], Cpp),
+            Cpp,
+        )
+        .into();
+        assert_eq!(&expected, stmt(&nodes[0]));
+
+        match stmt(&nodes[1]) {
+            Stmt::DoWhileStmt(do_while) => {
+                assert_eq!(1, do_while.body.nodes.len());
+                assert!(matches!(do_while.condition, Expr::BinaryExpr(_)));
+            }
+            stmt => panic!("Not a do-while: {:#?}", stmt),
+        }
+
+        // The label is a sibling of the statement it labels
+        let label: Stmt = LabelStmt::new("retry".into(), Cpp).into();
+        assert_eq!(&label, stmt(&nodes[2]));
+        match stmt(&nodes[3]) {
+            Stmt::IfStmt(if_stmt) => {
+                let goto: Stmt = GotoStmt::new("retry".into(), Cpp).into();
+                assert_eq!(vec![Node::from(goto)], if_stmt.body.nodes);
+                let else_if = &if_stmt.else_body.as_ref().unwrap().nodes;
+                match stmt(&else_if[0]) {
+                    Stmt::IfStmt(else_if) => {
+                        let ret: Stmt = ReturnStmt::new(None, Cpp).into();
+                        assert_eq!(vec![Node::from(ret)], else_if.body.nodes);
+                        assert_eq!(1, else_if.else_body.as_ref().unwrap().nodes.len());
+                    }
+                    stmt => panic!("Not an else if: {:#?}", stmt),
+                }
+            }
+            stmt => panic!("Not an if: {:#?}", stmt),
+        }
+    }
+
+    #[test]
+    fn switch_cases_fall_through() {
+        let nodes = lower_body(
+            r#"
+switch (code) {
+    case 1:
+    case 2:
+        retry();
+        break;
+    default:
+        fail();
+}
+"#,
+        );
+        let switch = match stmt(&nodes[0]) {
+            Stmt::ExprStmt(ExprStmt {
+                expr: Expr::SwitchExpr(switch),
+                ..
+            }) => switch,
+            stmt => panic!("Not a switch: {:#?}", stmt),
+        };
+        let cases: Vec<_> = switch
+            .cases
+            .iter()
+            .map(|case| (case.cond.is_some(), case.body.nodes.len()))
+            .collect();
+        assert_eq!(vec![(true, 0), (true, 2), (false, 1)], cases);
+    }
+
+    #[test]
+    fn structured_bindings_and_coroutines() {
+        let nodes = lower_body(
+            r#"
+for (auto& [key, value] : table) {
+    co_await send(key, 1);
+}
+co_return;
+"#,
+        );
+
+        let for_range = match stmt(&nodes[0]) {
+            Stmt::ForRangeStmt(for_range) => for_range,
+            stmt => panic!("Not a range-based for: {:#?}", stmt),
+        };
+        let variables = match &*for_range.init {
+            Stmt::DeclStmt(decl) => decl
+                .variables
+                .iter()
+                .map(|var| (var.var_type.as_deref(), &*var.ident.name))
+                .collect::<Vec<_>>(),
+            stmt => panic!("Not a declaration: {:#?}", stmt),
+        };
+        assert_eq!(
+            vec![(Some("auto&"), "key"), (Some("auto&"), "value")],
+            variables
+        );
+
+        let send: Expr = CallExpr::new(
+            Box::new(Ident::new("send".into(), Cpp).into()),
+            vec![
+                Ident::new("key".into(), Cpp).into(),
+                Literal::new("1".into(), Cpp).into(),
+            ],
+            Cpp,
+        )
+        .into();
+        let await_send: Expr =
+            UnaryExpr::new(Box::new(send), Op::Other("co_await".into()), Cpp).into();
+        let await_send: Stmt = await_send.into();
+        assert_eq!(vec![Node::from(await_send)], for_range.body.nodes);
+
+        let co_return: Stmt = ReturnStmt::new(None, Cpp).into();
+        assert_eq!(&co_return, stmt(&nodes[1]));
+    }
 }
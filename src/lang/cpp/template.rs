@@ -0,0 +1,285 @@
+use crate::parse::AST;
+use crate::prophet::*;
+
+use super::{find_components, transform_into_method, transform_into_nested_class, type_ident};
+
+/// Transforms a `template_declaration` into the class or function it declares, along with its
+/// template
+pub(super) fn transform_template(ast: AST, module_name: &str, path: &str) -> Vec<ComponentType> {
+    let parameters = template_parameters(&ast);
+    let declared = ast.children.iter().find(|child| {
+        matches!(
+            &*child.r#type,
+            "class_specifier" | "struct_specifier" | "function_definition"
+        )
+    });
+
+    match declared {
+        Some(class) if class.r#type != "function_definition" => {
+            match transform_into_nested_class(class, None, module_name, path) {
+                Some(mut class) => {
+                    // The template only applies to the outermost class, not its inner classes
+                    class.template = Some(template(parameters, &class.component.container_name));
+                    vec![ComponentType::ClassOrInterfaceComponent(class)]
+                }
+                None => vec![],
+            }
+        }
+        Some(function) => match transform_into_method(function, module_name, path) {
+            Some(mut method) => {
+                method.template = Some(template(parameters, &method.method_name));
+                vec![ComponentType::MethodComponent(method)]
+            }
+            None => vec![],
+        },
+        None => ast
+            .children
+            .into_iter()
+            .flat_map(|child| find_components(child, module_name, path))
+            .collect(),
+    }
+}
+
+/// Build the template of a class or function, linking specializations (named with their template
+/// arguments) to their primary template by name
+pub(super) fn template(parameters: Vec<TemplateParameter>, name: &str) -> Template {
+    match specialization(name) {
+        Some((primary, arguments)) => Template::new(parameters, Some(primary), arguments),
+        None => Template::new(parameters, None, vec![]),
+    }
+}
+
+/// Get the parameters of a `template_declaration`
+pub(super) fn template_parameters(ast: &AST) -> Vec<TemplateParameter> {
+    ast.find_child_by_type(&["template_parameter_list"])
+        .map_or_else(Vec::new, |list| {
+            list.children
+                .iter()
+                .filter_map(template_parameter)
+                .collect()
+        })
+}
+
+fn template_parameter(ast: &AST) -> Option<TemplateParameter> {
+    let name = |types: &[&str]| {
+        ast.find_child_by_type(types)
+            .map_or_else(String::new, |name| name.value.clone())
+    };
+    let default_value = || {
+        ast.children
+            .last()
+            .map(type_ident)
+            .filter(|value| !value.is_empty())
+    };
+    let parameter = match &*ast.r#type {
+        "type_parameter_declaration" => TemplateParameter::new(
+            name(&["type_identifier"]),
+            ast.children.first()?.value.clone(),
+            None,
+            false,
+        ),
+        "optional_type_parameter_declaration" => TemplateParameter::new(
+            name(&["type_identifier"]),
+            ast.children.first()?.value.clone(),
+            default_value(),
+            false,
+        ),
+        "variadic_type_parameter_declaration" => TemplateParameter::new(
+            name(&["type_identifier"]),
+            ast.children.first()?.value.clone(),
+            None,
+            true,
+        ),
+        "parameter_declaration" => TemplateParameter::new(
+            name(&["identifier"]),
+            type_ident(ast.children.first()?),
+            None,
+            false,
+        ),
+        "optional_parameter_declaration" => TemplateParameter::new(
+            name(&["identifier"]),
+            type_ident(ast.children.first()?),
+            default_value(),
+            false,
+        ),
+        "variadic_parameter_declaration" => TemplateParameter::new(
+            ast.find_child_by_type(&["variadic_declarator"])
+                .and_then(|declarator| declarator.find_child_by_type(&["identifier"]))
+                .map_or_else(String::new, |name| name.value.clone()),
+            type_ident(ast.children.first()?),
+            None,
+            true,
+        ),
+        "template_template_parameter_declaration" => {
+            let inner = ast.find_child_by_type(&[
+                "type_parameter_declaration",
+                "optional_type_parameter_declaration",
+                "variadic_type_parameter_declaration",
+            ])?;
+            TemplateParameter {
+                r#type: "template".into(),
+                ..template_parameter(inner)?
+            }
+        }
+        _ => return None,
+    };
+    Some(parameter)
+}
+
+/// Split the name of a specialization into its primary template and its template arguments
+/// (`Pool<Client<int>, 4>` gives `Pool`, and `Client<int>` and `4`)
+pub(super) fn specialization(name: &str) -> Option<(String, Vec<String>)> {
+    if !name.ends_with('>') || name.contains("operator") {
+        return None;
+    }
+
+    // Find the bracket opening the arguments, from the end
+    let mut depth = 0;
+    let mut open = None;
+    for (i, c) in name.char_indices().rev() {
+        match c {
+            '>' => depth += 1,
+            '<' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            open = Some(i);
+            break;
+        }
+    }
+    let open = open?;
+
+    let mut arguments = vec![];
+    let mut argument = String::new();
+    let mut depth = 0;
+    for c in name[open + 1..name.len() - 1].chars() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push(argument.trim().to_string());
+                argument.clear();
+                continue;
+            }
+            _ => {}
+        }
+        argument.push(c);
+    }
+    if !argument.trim().is_empty() {
+        arguments.push(argument.trim().to_string());
+    }
+    Some((name[..open].to_string(), arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::specialization;
+    use crate::prophet::*;
+    use crate::test_util::parse_files;
+
+    #[test]
+    fn splits_specialized_names() {
+        assert_eq!(
+            Some(("Pool".into(), vec!["Client<int, char>".into(), "4".into()])),
+            specialization("Pool<Client<int, char>, 4>")
+        );
+        assert_eq!(None, specialization("Pool"));
+        assert_eq!(None, specialization("operator>"));
+    }
+
+    #[test]
+    fn lowers_templates_and_specializations() {
+        let code = r#"
+namespace rpc {
+template <typename T, class U = int, int N = 3, typename... Rest>
+class ClientPool {
+public:
+    T* Pop();
+
+    template <typename F>
+    void Each(F callback) {}
+};
+
+template <>
+class ClientPool<ThriftClient<int>> {
+public:
+    ThriftClient<int>* Pop();
+};
+
+template <typename T>
+T Max(T a, T b) {
+    return a;
+}
+
+template <>
+int Max<int>(int a, int b) {
+    return b;
+}
+}
+"#;
+        let modules = parse_files(&[("src/pool.h", code)]);
+        let module = modules
+            .iter()
+            .find(|module| module.namespace.as_deref() == Some("rpc"))
+            .unwrap();
+
+        let pool = &module.classes[0];
+        assert_eq!("ClientPool", pool.component.container_name);
+        assert_eq!(
+            Some(Template::new(
+                vec![
+                    TemplateParameter::new("T".into(), "typename".into(), None, false),
+                    TemplateParameter::new("U".into(), "class".into(), Some("int".into()), false),
+                    TemplateParameter::new("N".into(), "int".into(), Some("3".into()), false),
+                    TemplateParameter::new("Rest".into(), "typename".into(), None, true),
+                ],
+                None,
+                vec![]
+            )),
+            pool.template
+        );
+        let each = &pool.component.methods[1];
+        assert_eq!("Each", each.method_name);
+        assert_eq!(
+            vec![TemplateParameter::new(
+                "F".into(),
+                "typename".into(),
+                None,
+                false
+            )],
+            each.template.as_ref().unwrap().parameters
+        );
+
+        let specialization = &module.classes[1];
+        assert_eq!(
+            "ClientPool<ThriftClient<int>>",
+            specialization.component.container_name
+        );
+        assert_eq!(
+            Some(Template::new(
+                vec![],
+                Some("rpc::ClientPool".into()),
+                vec!["ThriftClient<int>".into()]
+            )),
+            specialization.template
+        );
+
+        let functions: Vec<_> = module
+            .component
+            .methods
+            .iter()
+            .map(|method| {
+                let template = method.template.as_ref().unwrap();
+                (
+                    &*method.method_name,
+                    template.parameters.len(),
+                    template.primary.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![("Max", 1, None), ("Max<int>", 0, Some("rpc::Max"))],
+            functions
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::ast::Expr;
+use crate::ast::{walk_expr_mut, CallExpr, Expr, Ident, VisitMut};
 use crate::java::method_body::{log_unknown_tag, parse_assignment_pub};
 use crate::java::method_def::parse_method;
 use crate::java::modifier::{find_modifier, parse_modifiers, Modifier};
@@ -10,11 +10,62 @@ use crate::Language::Java;
 /// Handles class definition portions of the Java language, like fields,
 /// orchestrating the class body, etc.
 
-/// Parse a single class/interface/annotation/what have you's AST
+/// Parse a single class/interface/annotation/what have you's AST. A class declared in another
+/// is named after the class enclosing it, as in `Outer.Inner`
 pub(crate) fn parse_class(
     ast: &AST,
     package: &str,
     path: &str,
+    outer: Option<&str>,
+) -> Option<ClassOrInterfaceComponent> {
+    let instance_name = match ast.find_child_by_type(&["identifier"]) {
+        Some(identifier) => identifier.value.clone(),
+        None => "".into(),
+    };
+    let instance_name = match outer {
+        Some(outer) => format!("{}.{}", outer, instance_name),
+        None => instance_name,
+    };
+    parse_class_named(ast, instance_name, package, path)
+}
+
+/// Parse an anonymous class from the `object_creation_expression` instantiating it, recording the
+/// instantiated type as its base class
+fn parse_anonymous_class(
+    ast: &AST,
+    instance_name: String,
+    package: &str,
+    path: &str,
+) -> Option<ClassOrInterfaceComponent> {
+    let mut class = parse_class_named(ast, instance_name, package, path)?;
+    class
+        .base_classes
+        .push(BaseClass::new(find_type(ast), AccessorType::Public));
+    Some(class)
+}
+
+/// Stand-in for the name of an anonymous class at the expression instantiating it, as the class
+/// is only numbered once the whole class body enclosing it is seen. Replaced by the class's name,
+/// like `Outer$1`, before the class body is returned.
+pub(crate) fn anonymous_class_key(creation: &AST) -> String {
+    format!("$anonymous{:?}", creation.span)
+}
+
+/// Name of the class a member belongs to, taken from the `Name::ClassComponent` instance name
+/// of the member's component
+pub(crate) fn enclosing_class_name(component: &ComponentInfo) -> &str {
+    component
+        .instance_name
+        .split("::")
+        .next()
+        .unwrap_or_default()
+}
+
+fn parse_class_named(
+    ast: &AST,
+    instance_name: String,
+    package: &str,
+    path: &str,
 ) -> Option<ClassOrInterfaceComponent> {
     // Get container info
     let instance_type = match ast.find_child_by_type(&["class", "interface", "enum", "annotation"])
@@ -25,10 +76,6 @@ pub(crate) fn parse_class(
         },
         None => InstanceType::ClassComponent,
     };
-    let instance_name = match ast.find_child_by_type(&["identifier"]) {
-        Some(identifier) => identifier.value.clone(),
-        None => "".into(),
-    };
 
     let declaration_type = match instance_type {
         InstanceType::InterfaceComponent => ContainerType::Interface,
@@ -54,6 +101,8 @@ pub(crate) fn parse_class(
     let mut fields = vec![];
     let mut constructors = vec![];
     let mut methods = vec![];
+    let mut inner_classes = vec![];
+    let mut base_classes = vec![];
     let mut modifier = Modifier::new();
 
     // Find bounds
@@ -72,11 +121,23 @@ pub(crate) fn parse_class(
                 parse_class_body(
                     member,
                     &component,
+                    &instance_name,
                     &mut constructors,
                     &mut methods,
                     &mut fields,
+                    &mut inner_classes,
                 );
             }
+            "superclass" | "super_interfaces" | "extends_interfaces" => {
+                base_classes.append(&mut parse_base_classes(member))
+            }
+            // The name, or the instantiated type and arguments of an anonymous class
+            "identifier"
+            | "type_identifier"
+            | "generic_type"
+            | "scoped_type_identifier"
+            | "type_arguments"
+            | "argument_list" => {}
             unknown_type => log_unknown_tag(unknown_type, "class"),
         };
     }
@@ -92,36 +153,173 @@ pub(crate) fn parse_class(
             line_count: end - start + 1,
         },
         declaration_type,
+        base_classes,
+        template: None,
         annotations: modifier.annotations,
         constructors,
         field_components: fields,
+        inner_classes,
     })
 }
 
+/// Parse the classes extended or interfaces implemented by a class, from its `superclass`,
+/// `super_interfaces` or `extends_interfaces` node
+fn parse_base_classes(ast: &AST) -> Vec<BaseClass> {
+    ast.children
+        .iter()
+        .flat_map(|child| match &*child.r#type {
+            "type_list" | "interface_type_list" => child.children.iter().collect(),
+            _ => vec![child],
+        })
+        .filter(|r#type| {
+            matches!(
+                &*r#type.r#type,
+                "type_identifier" | "generic_type" | "scoped_type_identifier"
+            )
+        })
+        .map(|r#type| BaseClass::new(stringify_type(r#type), AccessorType::Public))
+        .collect()
+}
+
+/// Convert a (possibly qualified or generic) type, like `java.util.List<Order>`, to a string
+fn stringify_type(ast: &AST) -> String {
+    match ast.children.is_empty() {
+        true => ast.value.clone(),
+        false => ast.children.iter().map(stringify_type).collect(),
+    }
+}
+
 /// Parses the members of a class to find members, methods, blocks, etc.
 fn parse_class_body(
     ast: &AST,
     component: &ComponentInfo,
+    class_name: &str,
     constructors: &mut Vec<MethodComponent>,
     methods: &mut Vec<MethodComponent>,
     fields: &mut Vec<FieldComponent>,
+    inner_classes: &mut Vec<ClassOrInterfaceComponent>,
 ) {
+    // Anonymous classes are numbered in the order they appear, as in `Outer$1`
+    let mut creations = vec![];
+    find_anonymous_classes(ast, &mut creations);
+    let anonymous: Vec<(&AST, ClassOrInterfaceComponent)> = creations
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, creation)| {
+            let name = format!("{}${}", class_name, i + 1);
+            let class =
+                parse_anonymous_class(creation, name, &*component.package_name, &*component.path)?;
+            Some((creation, class))
+        })
+        .collect();
+    let mut link = LinkAnonymousClasses(&anonymous);
+
     // Traverse body
     for member in ast.children.iter() {
         match &*member.r#type {
             "constructor_declaration" | "static_initializer" => {
-                constructors.push(parse_method(member, component))
+                constructors.push(parse_member_method(member, component, &anonymous))
+            }
+            "method_declaration" => {
+                methods.push(parse_member_method(member, component, &anonymous))
+            }
+            "field_declaration" => {
+                for mut field in parse_field(member, component) {
+                    if let Some(expr) = field.expression.as_mut() {
+                        link.visit_expr(expr);
+                    }
+                    fields.push(field);
+                }
             }
-            "method_declaration" => methods.push(parse_method(member, component)),
-            "field_declaration" => fields.append(&mut parse_field(member, component)),
             "class_declaration"
             | "interface_declaration"
             | "enum_declaration"
-            | "annotation_declaration" => { /* None, since these were extracted + handled elsewhere */
-            }
+            | "record_declaration"
+            | "annotation_declaration"
+            | "annotation_type_declaration" => inner_classes.extend(parse_class(
+                member,
+                &*component.package_name,
+                &*component.path,
+                Some(class_name),
+            )),
             unknown => log_unknown_tag(unknown, "class body"),
         }
     }
+    inner_classes.extend(anonymous.into_iter().map(|(_, class)| class));
+}
+
+/// Parse a method or constructor, whose sub-methods include the methods of the anonymous
+/// classes instantiated in it
+fn parse_member_method(
+    ast: &AST,
+    component: &ComponentInfo,
+    anonymous: &[(&AST, ClassOrInterfaceComponent)],
+) -> MethodComponent {
+    let mut method = parse_method(ast, component);
+    LinkAnonymousClasses(anonymous).visit_method(&mut method);
+    for (_, class) in anonymous
+        .iter()
+        .filter(|(creation, _)| encloses(ast, creation))
+    {
+        method
+            .sub_methods
+            .extend(class.component.methods.iter().cloned());
+    }
+    method
+}
+
+/// Whether a node lies within the span of another
+fn encloses(outer: &AST, inner: &AST) -> bool {
+    match (outer.span, inner.span) {
+        (Some(outer), Some(inner)) => {
+            (outer.0, outer.1) <= (inner.0, inner.1) && (inner.2, inner.3) <= (outer.2, outer.3)
+        }
+        _ => false,
+    }
+}
+
+/// Names the anonymous classes of a class body at the expressions instantiating them, in place of
+/// the stand-ins given by `anonymous_class_key`
+struct LinkAnonymousClasses<'a, 'b>(&'a [(&'b AST, ClassOrInterfaceComponent)]);
+
+impl VisitMut for LinkAnonymousClasses<'_, '_> {
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        if let Expr::CallExpr(CallExpr { name, .. }) = expr {
+            if let Expr::Ident(Ident { name, .. }) = &mut **name {
+                let class = self
+                    .0
+                    .iter()
+                    .find(|(creation, _)| anonymous_class_key(creation) == *name);
+                if let Some((_, class)) = class {
+                    *name = class.component.container_name.clone();
+                }
+            }
+        }
+        walk_expr_mut(self, expr);
+    }
+}
+
+/// Find the anonymous classes instantiated in a class body, skipping the classes declared in it
+/// and the bodies of the anonymous classes themselves, which number their own
+fn find_anonymous_classes<'a>(ast: &'a AST, creations: &mut Vec<&'a AST>) {
+    for child in ast.children.iter() {
+        match &*child.r#type {
+            "class_declaration"
+            | "interface_declaration"
+            | "enum_declaration"
+            | "record_declaration"
+            | "annotation_declaration"
+            | "annotation_type_declaration"
+            | "class_body" => {}
+            "object_creation_expression" => {
+                if child.find_child_by_type(&["class_body"]).is_some() {
+                    creations.push(child);
+                }
+                find_anonymous_classes(child, creations);
+            }
+            _ => find_anonymous_classes(child, creations),
+        }
+    }
 }
 
 /// Parses a single field in a class
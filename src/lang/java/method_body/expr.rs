@@ -1,3 +1,4 @@
+use crate::java::class_def::anonymous_class_key;
 use crate::java::method_body::node::{parse_child_nodes, parse_node};
 use crate::java::method_body::parse_block;
 use crate::java::util::parameter::parse_method_parameters;
@@ -39,6 +40,8 @@ pub(crate) fn parse_expr(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
         "update_expression" => parse_inc_dec(ast, component),
         "unary_expression" => parse_unary(ast, component),
         "cast_expression" => parse_cast(ast, component),
+        "instanceof_expression" => parse_instanceof(ast, component),
+        "method_reference" => parse_method_reference(ast, component),
 
         // Base case
         unknown => {
@@ -163,6 +166,9 @@ fn parse_object_creation(ast: &AST, component: &ComponentInfo) -> Expr {
     for child in ast.children.iter() {
         match &*child.r#type {
             "type_identifier" => name = child.value.clone(),
+            // Anonymous classes are parsed with the class body enclosing them, and instantiated
+            // by their own name
+            "class_body" => name = anonymous_class_key(ast),
             "argument_list" => {
                 arg_list = parse_child_nodes(child, component)
                     .into_iter()
@@ -222,9 +228,15 @@ fn parse_array_access(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
 
 pub(crate) fn parse_lambda(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
     let mut params = vec![];
-    let mut body = None;
 
-    for child in ast.children.iter() {
+    // The body is last, and may be a single expression
+    let (body, parameters) = ast.children.split_last()?;
+    let body = match &*body.r#type {
+        "block" => parse_block(body, component),
+        _ => to_block(Node::Expr(parse_expr(body, component)?), Java),
+    };
+
+    for child in parameters.iter() {
         match &*child.r#type {
             "identifier" => params.push(new_simple_param(child)),
             "inferred_parameters" => {
@@ -263,11 +275,11 @@ pub(crate) fn parse_lambda(ast: &AST, component: &ComponentInfo) -> Option<Expr>
                 .concat();
             }
             "->" => { /* Ignore the boilerplate */ }
-            _ => body = Some(parse_block(child, component)),
+            unknown => log_unknown_tag(unknown, "lambda"),
         }
     }
 
-    Some(LambdaExpr::new(params, body?, Java).into())
+    Some(LambdaExpr::new(params, body, Java).into())
 }
 
 fn new_simple_param(ast: &AST) -> DeclStmt {
@@ -344,6 +356,36 @@ pub(crate) fn parse_switch(ast: &AST, component: &ComponentInfo) -> Option<Expr>
     Some(SwitchExpr::new(Box::new(condition?), cases, Java).into())
 }
 
+/// Parse a type test (`x instanceof Foo`)
+fn parse_instanceof(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
+    let expr = parse_expr(&ast.children[0], component)?;
+
+    // The tested type follows the keyword (and an optional `final`)
+    let checked_type = ast.children[1..]
+        .iter()
+        .find(|child| !matches!(&*child.r#type, "instanceof" | "final"))
+        .map(find_type)?;
+
+    Some(InstanceOfExpr::new(Box::new(expr), checked_type, Java).into())
+}
+
+/// Parse a method reference (`Foo::bar`, `this::bar`, `Foo::new`)
+fn parse_method_reference(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
+    let target = &ast.children[0];
+    let expr = match &*target.r#type {
+        "this" | "super" => Literal::new(target.value.clone(), Java).into(),
+        "type_identifier"
+        | "generic_type"
+        | "array_type"
+        | "integral_type"
+        | "floating_point_type"
+        | "boolean_type" => Ident::new(find_type(target), Java).into(),
+        _ => parse_expr(target, component)?,
+    };
+    let method = ast.children.last()?.value.clone();
+    Some(MethodRefExpr::new(Box::new(expr), method, Java).into())
+}
+
 /// Parse a ternary operator into our rendition of this structure
 fn parse_ternary(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
     let cond = parse_expr(&ast.children[0], component)?;
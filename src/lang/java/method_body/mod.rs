@@ -1,5 +1,5 @@
 use crate::ast::Expr;
-use crate::java::method_body::expr::parse_assignment;
+use crate::java::method_body::expr::{parse_assignment, parse_lambda};
 use crate::Language::Java;
 use crate::{ast::Block, ComponentInfo, AST};
 
@@ -42,3 +42,7 @@ pub(crate) fn is_common_junk_tag(tag: &str) -> bool {
 pub(crate) fn parse_assignment_pub(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
     parse_assignment(ast, component)
 }
+
+pub(crate) fn parse_lambda_pub(ast: &AST, component: &ComponentInfo) -> Option<Expr> {
+    parse_lambda(ast, component)
+}
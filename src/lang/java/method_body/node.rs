@@ -1,6 +1,9 @@
 use crate::ast::*;
 use crate::java::method_body::expr::parse_expr;
-use crate::java::method_body::stmt::{parse_decl, parse_enhanced_for, parse_for, parse_try_catch};
+use crate::java::method_body::stmt::{
+    parse_assert, parse_decl, parse_enhanced_for, parse_for, parse_local_class, parse_synchronized,
+    parse_try_catch,
+};
 use crate::ComponentInfo;
 use crate::Language::Java;
 use crate::AST;
@@ -35,6 +38,13 @@ pub(crate) fn parse_node(ast: &AST, component: &ComponentInfo) -> Option<Node> {
         "labeled_statement" => parse_labeled(ast, component),
         "return_statement" => parse_return(ast, component),
         "throw_statement" => parse_throw(ast, component),
+        "assert_statement" => parse_assert(ast, component),
+        "synchronized_statement" => parse_synchronized(ast, component),
+        "class_declaration"
+        | "interface_declaration"
+        | "enum_declaration"
+        | "record_declaration"
+        | "annotation_type_declaration" => parse_local_class(ast, component),
         "block" => Some(parse_block(ast, component).into()),
         _ => {
             let expr: Stmt = parse_expr(ast, component)?.into();
@@ -1,4 +1,5 @@
 use crate::ast::*;
+use crate::java::class_def::{enclosing_class_name, parse_class};
 use crate::java::method_body::log_unknown_tag;
 use crate::java::method_body::parse_block;
 use crate::java::method_body::parse_child_nodes;
@@ -387,3 +388,41 @@ pub(crate) fn parse_throw(ast: &AST, component: &ComponentInfo) -> Option<Node>
         ThrowStmt::new(parse_expr(&ast.children[1], component), Java).into(),
     ))
 }
+
+/// Parse an assertion, which may be followed by a message (`assert cond : message;`)
+pub(crate) fn parse_assert(ast: &AST, component: &ComponentInfo) -> Option<Node> {
+    let mut exprs = ast
+        .children
+        .iter()
+        .filter(|child| !matches!(&*child.r#type, "assert" | ":" | ";"))
+        .flat_map(|child| parse_expr(child, component));
+    let cond = exprs.next()?;
+    let message = exprs.next();
+    Some(Node::Stmt(AssertStmt::new(cond, message, Java).into()))
+}
+
+pub(crate) fn parse_synchronized(ast: &AST, component: &ComponentInfo) -> Option<Node> {
+    let mut lock = None;
+    let mut body = Block::new(vec![], Java);
+
+    for child in ast.children.iter() {
+        match &*child.r#type {
+            "parenthesized_expression" => lock = parse_expr(child, component),
+            "block" => body = parse_block(child, component),
+            "synchronized" => {}
+            unknown => log_unknown_tag(unknown, "synchronized"),
+        }
+    }
+    Some(Node::Stmt(SynchronizedStmt::new(lock?, body, Java).into()))
+}
+
+/// Parse a class, interface, enum or record declared inside of a method body
+pub(crate) fn parse_local_class(ast: &AST, component: &ComponentInfo) -> Option<Node> {
+    let class = parse_class(
+        ast,
+        &*component.package_name,
+        &*component.path,
+        Some(enclosing_class_name(component)),
+    )?;
+    Some(Node::Stmt(ClassDeclStmt::new(Box::new(class), Java).into()))
+}
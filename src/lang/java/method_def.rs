@@ -1,8 +1,10 @@
-use crate::java::method_body::parse_block;
+use crate::ast::{Expr, LambdaExpr};
+use crate::java::method_body::{parse_block, parse_lambda_pub};
 use crate::java::modifier::parse_modifiers;
 use crate::java::modifier::Modifier;
+use crate::java::util::fold_vec;
 use crate::java::util::parameter::parse_method_parameters;
-use crate::java::util::vartype::find_type;
+use crate::java::util::vartype::{find_type, NO_TYPE};
 use crate::parse::AST;
 use crate::prophet::*;
 use crate::Language::Java;
@@ -48,6 +50,14 @@ pub(crate) fn parse_method(ast: &AST, component: &ComponentInfo) -> MethodCompon
         }
     }
 
+    let is_override = modifier
+        .annotations
+        .iter()
+        .any(|annotation| annotation.name == "@Override");
+
+    // Lambdas declared in the method are its sub-methods
+    let sub_methods = parse_lambdas(ast, &component, &*method_name);
+
     // Define new component info
     let component = ComponentInfo {
         path: component.path.clone(),
@@ -67,7 +77,10 @@ pub(crate) fn parse_method(ast: &AST, component: &ComponentInfo) -> MethodCompon
         is_static: modifier.is_static,
         is_abstract: modifier.is_abstract,
         is_final: modifier.is_final,
-        sub_methods: vec![],
+        is_virtual: false,
+        is_override,
+        template: None,
+        sub_methods,
         annotations: modifier.annotations,
         line_count: line_end - line_begin,
         line_begin,
@@ -75,3 +88,95 @@ pub(crate) fn parse_method(ast: &AST, component: &ComponentInfo) -> MethodCompon
         body,
     }
 }
+
+/// Parse the lambdas declared in a method or lambda into methods, named as javac names them
+/// (`lambda$method$0`). Lambdas nested in these, or declared by classes, belong to those instead.
+fn parse_lambdas(ast: &AST, component: &ComponentInfo, method_name: &str) -> Vec<MethodComponent> {
+    let mut lambdas = vec![];
+    find_lambdas(ast, &mut lambdas);
+    lambdas
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, lambda)| {
+            let name = format!("lambda${}${}", method_name, i);
+            parse_lambda_method(lambda, component, name)
+        })
+        .collect()
+}
+
+fn find_lambdas<'a>(ast: &'a AST, lambdas: &mut Vec<&'a AST>) {
+    for child in ast.children.iter() {
+        match &*child.r#type {
+            "lambda_expression" => lambdas.push(child),
+            "class_body"
+            | "class_declaration"
+            | "interface_declaration"
+            | "enum_declaration"
+            | "record_declaration" => {}
+            _ => find_lambdas(child, lambdas),
+        }
+    }
+}
+
+/// Parse the AST for a lambda as a method
+fn parse_lambda_method(
+    ast: &AST,
+    component: &ComponentInfo,
+    method_name: String,
+) -> Option<MethodComponent> {
+    let (parameters, body) = match parse_lambda_pub(ast, component)? {
+        Expr::LambdaExpr(LambdaExpr {
+            parameters, body, ..
+        }) => (parameters, body),
+        _ => return None,
+    };
+
+    // Convert the declared parameters
+    let parameters = parameters
+        .into_iter()
+        .flat_map(|decl| decl.variables)
+        .map(|var| MethodParamComponent {
+            component: ComponentInfo {
+                path: component.path.clone(),
+                package_name: component.package_name.clone(),
+                instance_name: component.instance_name.clone(),
+                instance_type: InstanceType::MethodParamComponent,
+                language: Java,
+            },
+            annotation: fold_vec(var.annotation),
+            r#type: var.var_type.unwrap_or_else(|| NO_TYPE.into()),
+            parameter_name: var.ident.name,
+        })
+        .collect();
+
+    // Extract position
+    let span = ast.span.expect("No span for a lambda! AST malformed!");
+    let line_begin = span.0 as i32;
+    let line_end = span.2 as i32;
+
+    Some(MethodComponent {
+        component: ComponentInfo {
+            path: component.path.clone(),
+            package_name: component.package_name.clone(),
+            instance_name: method_name.clone(),
+            instance_type: InstanceType::MethodComponent,
+            language: Java,
+        },
+        accessor: AccessorType::Default,
+        sub_methods: parse_lambdas(ast, component, &*method_name),
+        method_name,
+        return_type: NO_TYPE.into(),
+        parameters,
+        is_static: false,
+        is_abstract: false,
+        is_final: false,
+        is_virtual: false,
+        is_override: false,
+        template: None,
+        annotations: vec![],
+        line_count: line_end - line_begin,
+        line_begin,
+        line_end,
+        body: Some(body),
+    })
+}
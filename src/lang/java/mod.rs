@@ -1,5 +1,6 @@
 use std::collections::{hash_map::Entry, HashMap};
 
+use crate::ast::ImportStmt;
 use crate::parse::AST;
 use crate::prophet::*;
 
@@ -9,11 +10,15 @@ use class_def::*;
 mod method_body;
 mod method_def;
 mod modifier;
-mod util;
+mod resolve;
+pub(crate) mod util;
+pub use resolve::*;
 
 /// Topmost level of the Java parser, provides public API
 
-pub fn merge_modules(modules: Vec<ModuleComponent>) -> Vec<ModuleComponent> {
+/// Resolve the types used by each file to their qualified names, then merge same-name modules
+pub fn merge_modules(mut modules: Vec<ModuleComponent>) -> Vec<ModuleComponent> {
+    resolve_types(&mut modules);
     let mut packages: HashMap<String, ModuleComponent> = HashMap::new();
 
     // Merge same-name modules
@@ -48,10 +53,18 @@ fn find_components_internal(ast: AST, mut package: String, path: &str) -> Vec<Co
         package = parse_package(node);
     }
 
+    // Find imports, to resolve types with
+    for node in ast
+        .find_all_children_by_type(&["import_declaration"])
+        .get_or_insert(vec![])
+        .iter()
+    {
+        components.push(ComponentType::ImportStmt(parse_import(node)));
+    }
+
     // Parse rest of object
     for node in ast
         .find_all_children_by_type(&[
-            // "import_declaration",
             "class_declaration",
             "interface_declaration",
             "enum_declaration",
@@ -60,7 +73,7 @@ fn find_components_internal(ast: AST, mut package: String, path: &str) -> Vec<Co
         .get_or_insert(vec![])
         .iter()
     {
-        if let Some(class) = parse_class(node, &*package, path) {
+        if let Some(class) = parse_class(node, &*package, path, None) {
             // Save the methods
             for method in class.component.methods.iter() {
                 components.push(ComponentType::MethodComponent(method.clone()));
@@ -88,14 +101,303 @@ fn do_parse_package_node(ast: &AST) -> String {
     }
 }
 
-// /// Take the AST node containing an import statement, and return back the String describing the package imported
-// fn parse_import(ast: &AST) -> String {
-//     let mut buffer = String::new();
-//     for child in ast.children.iter() {
-//         match &*child.r#type {
-//             "identifier" | "." | "*" => buffer.push_str(&*child.value),
-//             _ => buffer.push_str(&*parse_import(child)),
-//         };
-//     }
-//     buffer
-// }
+/// Take the AST node containing an import statement, and return the import it describes. Imports
+/// on demand (`import java.util.*`) are containers, and static imports are used directly.
+fn parse_import(ast: &AST) -> ImportStmt {
+    let value = ast
+        .find_child_by_type(&["scoped_identifier", "identifier"])
+        .map(do_parse_package_node)
+        .unwrap_or_default();
+    ImportStmt::new(
+        ast.find_child_by_type(&["asterisk", "*"]).is_some(),
+        ast.find_child_by_type(&["static"]).is_some(),
+        value,
+        Language::Java,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::*;
+    use crate::test_util::parse_java;
+
+    /// Lower the body of `OrderService.run`
+    fn lower_body(body: &str) -> Vec<Node> {
+        let code = format!("class OrderService {{\n void run() {{\n{}\n}}\n}}", body);
+        let modules = parse_java(&code);
+        let method = modules[0].classes[0]
+            .component
+            .methods
+            .iter()
+            .find(|method| method.method_name == "run")
+            .unwrap();
+        method.body.as_ref().unwrap().nodes.clone()
+    }
+
+    fn stmt(node: &Node) -> &Stmt {
+        match node {
+            Node::Stmt(stmt) => stmt,
+            node => panic!("Not a statement: {:#?}", node),
+        }
+    }
+
+    fn literal(expr: &Expr) -> &str {
+        match expr {
+            Expr::Literal(literal) => &literal.value,
+            expr => panic!("Not a literal: {:#?}", expr),
+        }
+    }
+
+    fn ident(expr: &Expr) -> &str {
+        match expr {
+            Expr::Ident(ident) => &ident.name,
+            expr => panic!("Not an identifier: {:#?}", expr),
+        }
+    }
+
+    #[test]
+    fn instanceof_tests_type() {
+        let nodes = lower_body("if (item instanceof Order) ship(item);");
+        let instance_of = match stmt(&nodes[0]) {
+            Stmt::IfStmt(IfStmt {
+                cond: Expr::InstanceOfExpr(instance_of),
+                ..
+            }) => instance_of,
+            stmt => panic!("Not a type test: {:#?}", stmt),
+        };
+        assert_eq!("item", ident(&instance_of.expr));
+        assert_eq!("Order", instance_of.checked_type);
+    }
+
+    #[test]
+    fn synchronized_and_assert() {
+        let nodes = lower_body(
+            r#"
+synchronized (lock) {
+    count++;
+}
+assert count > 0 : "empty";
+"#,
+        );
+        match stmt(&nodes[0]) {
+            Stmt::SynchronizedStmt(synchronized) => {
+                assert_eq!("lock", ident(&synchronized.lock));
+                assert_eq!(1, synchronized.body.nodes.len());
+            }
+            stmt => panic!("Not a synchronized block: {:#?}", stmt),
+        }
+        match stmt(&nodes[1]) {
+            Stmt::AssertStmt(assert) => {
+                assert!(matches!(assert.cond, Expr::BinaryExpr(_)));
+                assert_eq!("\"empty\"", literal(assert.message.as_ref().unwrap()));
+            }
+            stmt => panic!("Not an assertion: {:#?}", stmt),
+        }
+    }
+
+    #[test]
+    fn method_references() {
+        let nodes = lower_body(
+            r#"
+orders.forEach(System.out::println);
+orders.map(Order::total);
+orders.forEach(this::ship);
+"#,
+        );
+        let references: Vec<_> = nodes
+            .iter()
+            .map(|node| match stmt(node) {
+                Stmt::ExprStmt(ExprStmt {
+                    expr: Expr::CallExpr(call),
+                    ..
+                }) => match &call.args[0] {
+                    Expr::MethodRefExpr(reference) => reference,
+                    arg => panic!("Not a method reference: {:#?}", arg),
+                },
+                stmt => panic!("Not a call: {:#?}", stmt),
+            })
+            .collect();
+
+        assert!(matches!(&*references[0].expr, Expr::DotExpr(_)));
+        assert_eq!("println", references[0].method);
+        assert_eq!("Order", ident(&references[1].expr));
+        assert_eq!("total", references[1].method);
+        assert_eq!("this", literal(&references[2].expr));
+        assert_eq!("ship", references[2].method);
+    }
+
+    #[test]
+    fn local_classes() {
+        let nodes = lower_body(
+            r#"
+class Step {
+    void apply() {
+        log();
+    }
+}
+new Step().apply();
+"#,
+        );
+        match stmt(&nodes[0]) {
+            Stmt::ClassDeclStmt(ClassDeclStmt { class, .. }) => {
+                assert_eq!("OrderService.Step", class.component.container_name);
+                let apply = &class.component.methods[0];
+                assert_eq!("apply", apply.method_name);
+                assert_eq!(1, apply.body.as_ref().unwrap().nodes.len());
+            }
+            stmt => panic!("Not a local class: {:#?}", stmt),
+        }
+    }
+
+    #[test]
+    fn nested_inner_and_anonymous_classes() {
+        let modules = parse_java(
+            r#"
+class Outer {
+    static class Nested {
+        void a() {}
+    }
+
+    class Inner {
+        void b() {}
+    }
+
+    void run() {
+        Runnable task = new Runnable() {
+            public void run() {
+                log();
+            }
+        };
+    }
+}
+"#,
+        );
+        let outer = &modules[0].classes[0];
+        let names: Vec<_> = outer
+            .inner_classes
+            .iter()
+            .map(|class| class.component.container_name.as_str())
+            .collect();
+        assert_eq!(vec!["Outer.Nested", "Outer.Inner", "Outer$1"], names);
+
+        let anonymous = &outer.inner_classes[2].component.methods[0];
+        assert_eq!("run", anonymous.method_name);
+        assert_eq!(1, anonymous.body.as_ref().unwrap().nodes.len());
+
+        // The anonymous class is named where it is instantiated, and its methods are sub-methods
+        // of the method instantiating it
+        let run = &outer.component.methods[0];
+        match stmt(&run.body.as_ref().unwrap().nodes[0]) {
+            Stmt::DeclStmt(decl) => match &decl.expressions[0] {
+                Some(Expr::CallExpr(creation)) => assert_eq!("Outer$1", ident(&creation.name)),
+                expr => panic!("Not an instantiation: {:#?}", expr),
+            },
+            stmt => panic!("Not a declaration: {:#?}", stmt),
+        }
+        let sub_methods: Vec<_> = run
+            .sub_methods
+            .iter()
+            .map(|method| method.method_name.as_str())
+            .collect();
+        assert_eq!(vec!["run"], sub_methods);
+    }
+
+    #[test]
+    fn lambdas_are_sub_methods() {
+        let modules = parse_java(
+            r#"
+class Jobs {
+    void run() {
+        submit(() -> {
+            each(item -> log(item));
+        });
+        submit(x -> x);
+    }
+}
+"#,
+        );
+        let run = &modules[0].classes[0].component.methods[0];
+        let lambdas: Vec<_> = run
+            .sub_methods
+            .iter()
+            .map(|lambda| {
+                let parameters: Vec<_> = lambda
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.parameter_name.as_str())
+                    .collect();
+                (
+                    lambda.method_name.as_str(),
+                    parameters,
+                    lambda.body.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("lambda$run$0", vec![], true),
+                ("lambda$run$1", vec!["x"], true)
+            ],
+            lambdas
+        );
+
+        let nested: Vec<_> = run.sub_methods[0]
+            .sub_methods
+            .iter()
+            .map(|lambda| lambda.method_name.as_str())
+            .collect();
+        assert_eq!(vec!["lambda$lambda$run$0$0"], nested);
+
+        // The bodies of lambdas stay in the method
+        match stmt(&run.body.as_ref().unwrap().nodes[0]) {
+            Stmt::ExprStmt(ExprStmt { expr, .. }) => match expr {
+                Expr::CallExpr(call) => {
+                    assert!(matches!(call.args[0], Expr::LambdaExpr(_)))
+                }
+                expr => panic!("Not a call: {:#?}", expr),
+            },
+            stmt => panic!("Not an expression: {:#?}", stmt),
+        }
+    }
+
+    #[test]
+    fn records_base_classes_and_overrides() {
+        let modules = parse_java(
+            r#"
+class OrderList extends java.util.AbstractList<Order> implements Serializable, Comparable<OrderList> {
+    @Override
+    public Order get(int index) { return null; }
+
+    public int size() { return 0; }
+}
+
+interface Repository extends Closeable {}
+"#,
+        );
+        let classes = &modules[0].classes;
+        let bases = |class: &crate::ClassOrInterfaceComponent| {
+            class
+                .base_classes
+                .iter()
+                .map(|base| base.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                "java.util.AbstractList<Order>",
+                "Serializable",
+                "java.lang.Comparable<OrderList>"
+            ],
+            bases(&classes[0])
+        );
+        assert_eq!(vec!["Closeable"], bases(&modules[0].interfaces[0]));
+
+        let overrides: Vec<_> = classes[0]
+            .component
+            .methods
+            .iter()
+            .map(|method| (method.method_name.as_str(), method.is_override))
+            .collect();
+        assert_eq!(vec![("get", true), ("size", false)], overrides);
+    }
+}
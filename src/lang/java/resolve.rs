@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::ast::*;
+use crate::java::util::vartype::NO_TYPE;
+use crate::prophet::*;
+
+/// Matches the (possibly qualified) names in a type, like `List` and `Order` in `List<Order>[]`
+static TYPE_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[A-Za-z_$][\w$]*(\.[A-Za-z_$][\w$]*)*").unwrap());
+
+/// Names that are not resolved, since they are not classes
+const KEYWORDS: &[&str] = &[
+    "boolean", "byte", "char", "short", "int", "long", "float", "double", "void", "var", "extends",
+    "super",
+];
+
+/// Classes of `java.lang`, which are imported implicitly
+const JAVA_LANG: &[&str] = &[
+    "AssertionError",
+    "AutoCloseable",
+    "Boolean",
+    "Byte",
+    "CharSequence",
+    "Character",
+    "Class",
+    "ClassCastException",
+    "ClassNotFoundException",
+    "CloneNotSupportedException",
+    "Cloneable",
+    "Comparable",
+    "Deprecated",
+    "Double",
+    "Enum",
+    "Error",
+    "Exception",
+    "Float",
+    "FunctionalInterface",
+    "IllegalArgumentException",
+    "IllegalStateException",
+    "IndexOutOfBoundsException",
+    "Integer",
+    "InterruptedException",
+    "Iterable",
+    "Long",
+    "Math",
+    "NullPointerException",
+    "Number",
+    "NumberFormatException",
+    "Object",
+    "Override",
+    "Process",
+    "Record",
+    "Runnable",
+    "Runtime",
+    "RuntimeException",
+    "SecurityException",
+    "Short",
+    "String",
+    "StringBuffer",
+    "StringBuilder",
+    "SuppressWarnings",
+    "System",
+    "Thread",
+    "ThreadLocal",
+    "Throwable",
+    "UnsupportedOperationException",
+    "Void",
+];
+
+/// Replaces the types of fields, parameters, return values and variables with their fully
+/// qualified names (`List<Order>` becomes `java.util.List<com.shop.Order>`), so that classes
+/// sharing a name across packages can be told apart. Each module is expected to hold a single
+/// file, as its imports are used to resolve the types of its classes. Names are looked up as
+/// the compiler would: classes nested in the enclosing classes first, then single-type imports,
+/// classes of the same package, on-demand imports of project packages, and `java.lang`. Names
+/// that cannot be resolved, like type parameters and classes of unknown packages, are left as is.
+pub fn resolve_types(modules: &mut [ModuleComponent]) {
+    let mut classes = HashSet::new();
+    for module in modules.iter() {
+        for class in module.classes.iter().chain(module.interfaces.iter()) {
+            add_project_class(&mut classes, class);
+        }
+    }
+
+    for module in modules.iter_mut() {
+        let mut resolver = TypeResolver::new(&classes, &module.imports);
+        for class in module
+            .classes
+            .iter_mut()
+            .chain(module.interfaces.iter_mut())
+        {
+            resolver.resolve_class(class);
+        }
+        for method in module.component.methods.iter_mut() {
+            // The methods of classes are also listed by their module, and resolved in their class
+            let declared = module
+                .classes
+                .iter()
+                .chain(module.interfaces.iter())
+                .flat_map(|class| class.component.methods.iter())
+                .find(|declared| {
+                    declared.method_name == method.method_name
+                        && declared.line_begin == method.line_begin
+                });
+            match declared {
+                Some(declared) => *method = declared.clone(),
+                None => {
+                    resolver.package = method.component.package_name.clone();
+                    resolver.resolve_method(method);
+                }
+            }
+        }
+    }
+}
+
+/// Record the qualified name of a class and the classes declared in it
+fn add_project_class(classes: &mut HashSet<String>, class: &ClassOrInterfaceComponent) {
+    classes.insert(qualify(
+        &class.component.component.package_name,
+        &class.component.container_name,
+    ));
+    for inner_class in class.inner_classes.iter() {
+        add_project_class(classes, inner_class);
+    }
+}
+
+fn qualify(package: &str, name: &str) -> String {
+    match package {
+        "" => name.to_string(),
+        package => format!("{}.{}", package, name),
+    }
+}
+
+/// Resolves the names used in a single file
+struct TypeResolver<'a> {
+    /// Qualified names of the project's classes
+    classes: &'a HashSet<String>,
+    /// Single-type imports, by the name they are used with
+    imports: HashMap<&'a str, &'a str>,
+    /// Packages (or classes) whose members are imported on demand
+    on_demand: Vec<&'a str>,
+    /// Package of the names being resolved
+    package: String,
+    /// Qualified names of the classes enclosing the names being resolved, innermost last
+    enclosing: Vec<String>,
+}
+
+impl<'a> TypeResolver<'a> {
+    fn new(classes: &'a HashSet<String>, imports: &'a [ImportStmt]) -> TypeResolver<'a> {
+        let mut single = HashMap::new();
+        let mut on_demand = vec![];
+        for import in imports.iter() {
+            if import.container {
+                on_demand.push(&*import.value);
+            } else if !import.use_direct {
+                let name = import.value.rsplit('.').next().unwrap_or_default();
+                single.insert(name, &*import.value);
+            }
+        }
+        TypeResolver {
+            classes,
+            imports: single,
+            on_demand,
+            package: String::new(),
+            enclosing: vec![],
+        }
+    }
+
+    fn resolve_class(&mut self, class: &mut ClassOrInterfaceComponent) {
+        self.package = class.component.component.package_name.clone();
+        self.enclosing
+            .push(qualify(&self.package, &class.component.container_name));
+
+        for base_class in class.base_classes.iter_mut() {
+            base_class.name = self.resolve(&base_class.name);
+        }
+        for field in class.field_components.iter_mut() {
+            field.r#type = self.resolve(&field.r#type);
+        }
+        walk_class_mut(self, class);
+
+        self.enclosing.pop();
+    }
+
+    fn resolve_method(&mut self, method: &mut MethodComponent) {
+        method.return_type = self.resolve(&method.return_type);
+        for parameter in method.parameters.iter_mut() {
+            parameter.r#type = self.resolve(&parameter.r#type);
+        }
+        walk_method_mut(self, method);
+    }
+
+    /// Resolve each of the names in a type
+    fn resolve(&self, r#type: &str) -> String {
+        if r#type == NO_TYPE {
+            return r#type.to_string();
+        }
+        TYPE_NAME
+            .replace_all(r#type, |captures: &Captures| {
+                let name = &captures[0];
+                self.resolve_name(name).unwrap_or_else(|| name.to_string())
+            })
+            .into_owned()
+    }
+
+    /// Resolve a name, whose first part may be a class (`Map.Entry`)
+    fn resolve_name(&self, name: &str) -> Option<String> {
+        if KEYWORDS.contains(&name) {
+            return None;
+        }
+        match name.split_once('.') {
+            Some((first, rest)) => Some(format!("{}.{}", self.resolve_simple_name(first)?, rest)),
+            None => self.resolve_simple_name(name),
+        }
+    }
+
+    fn resolve_simple_name(&self, name: &str) -> Option<String> {
+        // Classes declared in (or being) an enclosing class shadow the rest
+        for enclosing in self.enclosing.iter().rev() {
+            let nested = format!("{}.{}", enclosing, name);
+            if self.classes.contains(&nested) {
+                return Some(nested);
+            }
+            if enclosing.rsplit(|c: char| c == '.' || c == '$').next() == Some(name) {
+                return Some(enclosing.clone());
+            }
+        }
+
+        if let Some(import) = self.imports.get(name) {
+            return Some(import.to_string());
+        }
+
+        let same_package = qualify(&self.package, name);
+        if self.classes.contains(&same_package) {
+            return Some(same_package);
+        }
+
+        let on_demand = self
+            .on_demand
+            .iter()
+            .map(|package| qualify(package, name))
+            .find(|qualified| self.classes.contains(qualified));
+        if on_demand.is_some() {
+            return on_demand;
+        }
+
+        if JAVA_LANG.contains(&name) {
+            return Some(qualify("java.lang", name));
+        }
+        None
+    }
+}
+
+impl VisitMut for TypeResolver<'_> {
+    fn visit_var_decl(&mut self, var_decl: &mut VarDecl) {
+        if let Some(var_type) = var_decl.var_type.as_mut() {
+            *var_type = self.resolve(var_type);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        if let Expr::InstanceOfExpr(instance_of) = expr {
+            instance_of.checked_type = self.resolve(&instance_of.checked_type);
+        }
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_class(&mut self, class: &mut ClassOrInterfaceComponent) {
+        self.resolve_class(class);
+    }
+
+    fn visit_method(&mut self, method: &mut MethodComponent) {
+        self.resolve_method(method);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::*;
+    use crate::test_util::parse_files;
+    use crate::ModuleComponent;
+
+    const ORDER: &str = r#"
+package com.shop.order;
+
+import java.util.List;
+import com.shop.user.*;
+
+public class Order {
+    private String id;
+    private List<Item> items;
+    private User owner;
+
+    public Status check(Map<String, Item> index, T value) {
+        Item first = items.get(0);
+        return Status.OPEN;
+    }
+
+    class Item {
+        private Order order;
+    }
+
+    enum Status {
+        OPEN
+    }
+}
+"#;
+
+    const USER: &str = r#"
+package com.shop.user;
+
+public class User {
+    private String name;
+}
+"#;
+
+    const TEXT: &str = r#"
+package com.shop.text;
+
+class String {
+}
+
+class Label {
+    private String value;
+}
+"#;
+
+    fn class<'a>(
+        modules: &'a [ModuleComponent],
+        name: &str,
+    ) -> &'a crate::ClassOrInterfaceComponent {
+        modules
+            .iter()
+            .flat_map(|module| module.classes.iter())
+            .flat_map(|class| std::iter::once(class).chain(class.inner_classes.iter()))
+            .find(|class| class.component.container_name == name)
+            .unwrap()
+    }
+
+    fn field_types(modules: &[ModuleComponent], name: &str) -> Vec<String> {
+        class(modules, name)
+            .field_components
+            .iter()
+            .map(|field| field.r#type.clone())
+            .collect()
+    }
+
+    #[test]
+    fn resolves_fully_qualified_types() {
+        let modules = parse_files(&[
+            ("order/Order.java", ORDER),
+            ("user/User.java", USER),
+            ("text/Text.java", TEXT),
+        ]);
+
+        assert_eq!(
+            vec![
+                "java.lang.String",
+                "java.util.List<com.shop.order.Order.Item>",
+                "com.shop.user.User"
+            ],
+            field_types(&modules, "Order")
+        );
+        assert_eq!(
+            vec!["com.shop.order.Order"],
+            field_types(&modules, "Order.Item")
+        );
+
+        // Classes of the same package shadow java.lang
+        assert_eq!(vec!["java.lang.String"], field_types(&modules, "User"));
+        assert_eq!(vec!["com.shop.text.String"], field_types(&modules, "Label"));
+
+        let check = &class(&modules, "Order").component.methods[0];
+        assert_eq!("com.shop.order.Order.Status", check.return_type);
+        let module_check = modules
+            .iter()
+            .flat_map(|module| module.component.methods.iter())
+            .find(|method| method.method_name == "check")
+            .unwrap();
+        assert_eq!(check, module_check);
+        let parameters: Vec<_> = check.parameters.iter().map(|p| &*p.r#type).collect();
+        assert_eq!(
+            vec!["Map<java.lang.String,com.shop.order.Order.Item>", "T"],
+            parameters
+        );
+
+        let body = check.body.as_ref().unwrap();
+        match &body.nodes[0] {
+            Node::Stmt(Stmt::DeclStmt(decl)) => assert_eq!(
+                Some("com.shop.order.Order.Item"),
+                decl.variables[0].var_type.as_deref()
+            ),
+            node => panic!("Not a declaration: {:#?}", node),
+        }
+    }
+}
@@ -13,6 +13,9 @@ pub mod communication;
 
 pub mod ressa;
 
+#[cfg(test)]
+mod test_util;
+
 // Wow I hate this and it is terrible but it works for fixing
 // our crate name resolution error with regards to using our own
 // proc macros
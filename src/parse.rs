@@ -1,12 +1,14 @@
 use derive_new::new;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
 use rust_code_analysis::{
-    action, guess_language, AstCallback, AstCfg, AstPayload, AstResponse, Span, LANG,
+    action, get_language_for_file, guess_language, AstCallback, AstCfg, AstPayload, AstResponse,
+    Span, LANG,
 };
 
 use crate::{communication::*, lang::*, *};
@@ -81,12 +83,34 @@ impl From<AstResponse> for AST {
     }
 }
 
+/// Options for lowering source code into the LAAST
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ParseOptions {
+    /// Macros defined for the C++ preprocessor, by name, with their replacement text (which may
+    /// be empty). They decide which branches of `#if` directives are kept.
+    #[serde(default)]
+    pub defines: BTreeMap<String, String>,
+}
+
 impl AST {
     /// Transform the language-specific AST into generic components.
     pub fn transform(self, lang: LANG, path: &str) -> (Vec<ComponentType>, Language) {
+        self.transform_with_options(lang, path, &ParseOptions::default())
+    }
+
+    /// Transform the language-specific AST into generic components, with the provided options
+    pub fn transform_with_options(
+        self,
+        lang: LANG,
+        path: &str,
+        options: &ParseOptions,
+    ) -> (Vec<ComponentType>, Language) {
         // Do language specific AST parsing
         match lang {
-            LANG::Cpp => (cpp::find_components(self, path, path), lang.into()),
+            LANG::Cpp => {
+                let ast = cpp::Preprocessor::new(&options.defines).process(self);
+                (cpp::find_components(ast, path, path), lang.into())
+            }
             LANG::Java => (java::find_components(self, path), lang.into()),
             LANG::Python => (vec![], Language::Python),
             LANG::Go => (vec![], Language::Go),
@@ -107,13 +131,48 @@ pub struct Directory {
     path: PathBuf,
 }
 
+impl Directory {
+    /// Describe the directory at `path` as found on disk, listing the files of a recognized
+    /// language in it and its subdirectories. Hidden files and directories are skipped.
+    pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Directory> {
+        let path = path.as_ref().to_path_buf();
+        let mut files = vec![];
+        let mut sub_directories = vec![];
+
+        let mut entries = std::fs::read_dir(&path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            let hidden = entry
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if entry.is_dir() {
+                sub_directories.push(Directory::read(&entry)?);
+            } else if get_language_for_file(&entry).is_some() {
+                files.push(entry);
+            }
+        }
+
+        Ok(Directory::new(files, sub_directories, path))
+    }
+}
+
 pub fn parse_project_context_compat(directory: &Directory) -> std::io::Result<compat::JSSAContext> {
     Ok(parse_project_context(directory)?.into())
 }
 
 pub fn parse_project_context(directory: &Directory) -> std::io::Result<JSSAContext> {
-    let modules = parse_directory_into_laast(directory)?;
-    let ctx = JSSAContext {
+    Ok(project_context(parse_directory_into_laast(directory)?))
+}
+
+/// Build the analysis context of already parsed modules
+pub fn project_context(modules: Vec<ModuleComponent>) -> JSSAContext<'static> {
+    JSSAContext {
         component: ComponentInfo {
             path: "".into(),
             package_name: "".into(),
@@ -124,8 +183,7 @@ pub fn parse_project_context(directory: &Directory) -> std::io::Result<JSSAConte
         succeeded: true,
         root_path: "",
         modules,
-    };
-    Ok(ctx)
+    }
 }
 
 fn flatten_dirs(dir: &Directory) -> Vec<Directory> {
@@ -144,11 +202,43 @@ pub struct ParsedTree {
 }
 
 pub fn parse_directory_into_laast(dir: &Directory) -> std::io::Result<Vec<ModuleComponent>> {
-    convert_trees_to_laast(parse_directory_trees(dir)?)
+    parse_directory_into_laast_with_options(dir, &ParseOptions::default())
+}
+
+/// Parse the directory like `parse_directory_into_laast`, with the provided options
+pub fn parse_directory_into_laast_with_options(
+    dir: &Directory,
+    options: &ParseOptions,
+) -> std::io::Result<Vec<ModuleComponent>> {
+    let (modules, language) = lower_trees(directory_trees(dir)?, options)?;
+    Ok(merge_modules(modules, language))
 }
 
 pub fn parse_directory_trees(dir: &Directory) -> std::io::Result<Vec<ParsedTree>> {
-    let mut parsed_trees = vec![];
+    let parsed_trees = directory_trees(dir)?.collect();
+    tracing::info!("Finished parsing files!");
+    parsed_trees
+}
+
+/// Parse the source files of the directory, one at a time as the trees are requested
+fn directory_trees(
+    dir: &Directory,
+) -> std::io::Result<impl Iterator<Item = std::io::Result<ParsedTree>>> {
+    Ok(directory_files(dir)?
+        .into_iter()
+        .filter_map(|(file, module_name, path)| {
+            let mut code = String::new();
+            if let Err(err) = File::open(&file).and_then(|mut file| file.read_to_string(&mut code))
+            {
+                return Some(Err(err));
+            }
+            parse_tree(&file, code, &module_name, &path).map(Ok)
+        }))
+}
+
+/// List the files of the directory to parse, with the name and path of their module
+fn directory_files(dir: &Directory) -> std::io::Result<Vec<(PathBuf, String, String)>> {
+    let mut files = vec![];
 
     let dirs = flatten_dirs(dir);
 
@@ -195,51 +285,96 @@ pub fn parse_directory_trees(dir: &Directory) -> std::io::Result<Vec<ParsedTree>
             if !dir.files.iter().any(|path_buf| path_buf == &entry.path()) {
                 continue;
             }
-            let mut file = File::open(entry.path())?;
-            let mut code = String::new();
-            file.read_to_string(&mut code)?;
-
-            tracing::info!(
-                "Parsing file: {:?}",
-                entry.path().to_str().unwrap_or_default()
-            );
-            let result = parse_ast(AstPayload {
-                id: "".to_owned(),
-                file_name: entry.path().to_str().unwrap_or("").to_owned(),
-                code,
-                comment: false,
-                span: true,
-            });
-
-            let (ast, lang) = match result {
-                Some((ast, lang)) => (ast, lang),
-                None => {
-                    tracing::warn!("Could not parse AST {:?}", entry.path());
-                    continue;
-                }
-            };
-
-            parsed_trees.push(ParsedTree::new(
-                ast,
-                lang,
-                module_name.clone(),
-                path.clone().into(),
-            ));
+            files.push((entry.path(), module_name.clone(), path.clone()));
         }
     }
 
+    Ok(files)
+}
+
+/// Parse in-memory source files, by path, into the LAAST. Files are grouped into modules by
+/// their directory, as if they were read from disk by `parse_directory_into_laast`.
+pub fn parse_sources_into_laast(
+    sources: &BTreeMap<PathBuf, String>,
+) -> std::io::Result<Vec<ModuleComponent>> {
+    parse_sources_into_laast_with_options(sources, &ParseOptions::default())
+}
+
+/// Parse in-memory source files like `parse_sources_into_laast`, with the provided options
+pub fn parse_sources_into_laast_with_options(
+    sources: &BTreeMap<PathBuf, String>,
+    options: &ParseOptions,
+) -> std::io::Result<Vec<ModuleComponent>> {
+    convert_trees_to_laast_with_options(parse_source_trees(sources), options)
+}
+
+pub fn parse_source_trees(sources: &BTreeMap<PathBuf, String>) -> Vec<ParsedTree> {
+    let trees: Vec<_> = sources
+        .iter()
+        .filter_map(|(file, code)| {
+            let dir = file
+                .parent()
+                .and_then(|dir| dir.to_str())
+                .unwrap_or_default();
+            parse_tree(file, code.clone(), dir, dir)
+        })
+        .collect();
+
     tracing::info!("Finished parsing files!");
-    Ok(parsed_trees)
+    trees
+}
+
+/// Parse a source file into the tree of a module, if its language is recognized
+fn parse_tree(file: &Path, code: String, module_name: &str, path: &str) -> Option<ParsedTree> {
+    tracing::info!("Parsing file: {:?}", file.to_str().unwrap_or_default());
+    let result = parse_ast(AstPayload {
+        id: "".to_owned(),
+        file_name: file.to_str().unwrap_or("").to_owned(),
+        code,
+        comment: false,
+        span: true,
+    });
+
+    match result {
+        Some((ast, lang)) => Some(ParsedTree::new(
+            ast,
+            lang,
+            module_name.to_owned(),
+            path.into(),
+        )),
+        None => {
+            tracing::warn!("Could not parse AST {:?}", file);
+            None
+        }
+    }
 }
 
 pub fn convert_trees_to_laast(
     parsed_trees: Vec<ParsedTree>,
 ) -> std::io::Result<Vec<ModuleComponent>> {
+    convert_trees_to_laast_with_options(parsed_trees, &ParseOptions::default())
+}
+
+/// Lower the parsed trees like `convert_trees_to_laast`, with the provided options
+pub fn convert_trees_to_laast_with_options(
+    parsed_trees: Vec<ParsedTree>,
+    options: &ParseOptions,
+) -> std::io::Result<Vec<ModuleComponent>> {
+    let (modules, language) = lower_trees(parsed_trees.into_iter().map(Ok), options)?;
+    Ok(merge_modules(modules, language))
+}
+
+/// Lower each tree into its module as it is read, before the modules are merged
+fn lower_trees<I: IntoIterator<Item = std::io::Result<ParsedTree>>>(
+    parsed_trees: I,
+    options: &ParseOptions,
+) -> std::io::Result<(Vec<ModuleComponent>, Language)> {
     let get_path_string = |path_buf: &PathBuf| path_buf.as_path().to_str().unwrap_or("").to_owned();
     let mut modules: Vec<ModuleComponent> = vec![];
     let mut language = Language::Unknown;
 
     for parsed_tree in parsed_trees {
+        let parsed_tree = parsed_tree?;
         let path = get_path_string(&parsed_tree.path);
         let mut module = ModuleComponent::new(
             parsed_tree.module_name.clone(),
@@ -247,9 +382,10 @@ pub fn convert_trees_to_laast(
             parsed_tree.lang.into(),
         );
 
-        let (components, lang) = parsed_tree.ast.transform(
+        let (components, lang) = parsed_tree.ast.transform_with_options(
             parsed_tree.lang,
             parsed_tree.path.to_str().unwrap_or_default(),
+            options,
         );
 
         match lang {
@@ -278,6 +414,12 @@ pub fn convert_trees_to_laast(
                 ComponentType::MethodComponent(method) => {
                     module.component.methods.push(method);
                 }
+                ComponentType::ImportStmt(import) => {
+                    module.imports.push(import);
+                }
+                ComponentType::MacroDefinition(definition) => {
+                    module.macros.push(definition);
+                }
                 ComponentType::ModuleComponent(module) => {
                     modules.push(module);
                 }
@@ -288,48 +430,67 @@ pub fn convert_trees_to_laast(
         modules.push(module);
     }
 
-    Ok(merge_modules(modules, language))
+    Ok((modules, language))
 }
 
 fn merge_modules(modules: Vec<ModuleComponent>, lang: Language) -> Vec<ModuleComponent> {
-    let modules = match lang {
+    convert_rpc_and_rest_calls(resolve_modules(modules, lang))
+}
+
+/// Merge the modules and type them, before the calls to other services can be found
+fn resolve_modules(modules: Vec<ModuleComponent>, lang: Language) -> Vec<ModuleComponent> {
+    let mut modules = match lang {
         Language::Cpp => cpp::merge_modules(modules),
         Language::Java => java::merge_modules(modules),
         _ => modules,
     };
 
-    convert_rpc_and_rest_calls(modules)
+    // Type the receivers of calls before looking for the services they call
+    ast::infer_types(&mut modules);
+    modules
 }
 
 fn convert_rpc_and_rest_calls(mut modules: Vec<ModuleComponent>) -> Vec<ModuleComponent> {
     let modules_view = modules.clone();
     for module in modules.iter_mut() {
-        let module_view = module.clone();
-        for class in module.classes.iter_mut() {
-            let class_view = class.clone();
-            for method in class.component.methods.iter_mut() {
-                let method_view = method.clone();
-                if let Some(body) = method.body.as_mut() {
-                    body.replace_communication_call(
-                        &modules_view,
-                        &module_view,
-                        Some(&class_view),
-                        &method_view,
-                    );
-                }
-            }
-        }
-        for method in module.component.methods.iter_mut() {
-            let method_view = method.clone();
-            if let Some(body) = method.body.as_mut() {
-                body.replace_communication_call(&modules_view, &module_view, None, &method_view);
-            }
-        }
+        convert_module_rpc_and_rest_calls(module, &modules_view);
     }
 
     modules
 }
 
+/// Convert the calls in the methods of a module, and of its classes
+fn convert_module_rpc_and_rest_calls(module: &mut ModuleComponent, modules: &[ModuleComponent]) {
+    let module_view = module.clone();
+    for class in module.classes.iter_mut() {
+        convert_class_rpc_and_rest_calls(class, modules, &module_view);
+    }
+    for method in module.component.methods.iter_mut() {
+        let method_view = method.clone();
+        if let Some(body) = method.body.as_mut() {
+            body.replace_communication_call(modules, &module_view, None, &method_view);
+        }
+    }
+}
+
+/// Convert the calls in the methods of a class, and of the classes declared in it
+fn convert_class_rpc_and_rest_calls(
+    class: &mut ClassOrInterfaceComponent,
+    modules: &[ModuleComponent],
+    module: &ModuleComponent,
+) {
+    let class_view = class.clone();
+    for method in class.component.methods.iter_mut() {
+        let method_view = method.clone();
+        if let Some(body) = method.body.as_mut() {
+            body.replace_communication_call(modules, module, Some(&class_view), &method_view);
+        }
+    }
+    for inner_class in class.inner_classes.iter_mut() {
+        convert_class_rpc_and_rest_calls(inner_class, modules, module);
+    }
+}
+
 pub fn parse_file(file: &mut File, path: &Path) -> std::io::Result<(Vec<ComponentType>, Language)> {
     let mut code = String::new();
     file.read_to_string(&mut code)?;
@@ -485,4 +646,39 @@ mod tests {
 
         assert!(root_node.is_some());
     }
+
+    #[test]
+    fn groups_sources_into_modules_by_directory() {
+        let sources: BTreeMap<_, _> = [
+            ("order/OrderService.java", "class OrderService {}"),
+            ("order/Order.java", "class Order {}"),
+            ("payment/PaymentService.java", "class PaymentService {}"),
+            ("README.md", "Not source code"),
+        ]
+        .iter()
+        .map(|(path, code)| (path.into(), code.to_string()))
+        .collect();
+
+        let mut modules = parse_sources_into_laast(&sources).unwrap();
+        modules.sort_by(|a, b| a.module_name.cmp(&b.module_name));
+        let classes = modules
+            .iter()
+            .map(|module| {
+                let mut names: Vec<_> = module
+                    .classes
+                    .iter()
+                    .map(|class| class.component.container_name.as_str())
+                    .collect();
+                names.sort_unstable();
+                (module.module_name.as_str(), names)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("order", vec!["Order", "OrderService"]),
+                ("payment", vec!["PaymentService"])
+            ],
+            classes
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use super::*;
-use crate::ast::Block;
+use crate::ast::{Block, ImportStmt};
 use serde::Serialize;
 
 #[derive(Debug, Serialize)]
@@ -27,25 +27,7 @@ impl From<super::JSSAContext<'_>> for JSSAContext {
             .flat_map(|module| module.classes.clone())
             .collect();
 
-        let funcs: Vec<_> = {
-            let constructors: Vec<_> = classes
-                .iter()
-                .flat_map(|class| class.constructors.clone())
-                .collect();
-
-            let module_functions: Vec<_> = other
-                .modules
-                .iter()
-                .flat_map(|module| module.component.methods.clone())
-                .collect();
-
-            classes
-                .iter()
-                .flat_map(|class| class.component.methods.clone())
-                .chain(constructors)
-                .chain(module_functions)
-                .collect()
-        };
+        let funcs = functions(&classes, &other.modules);
 
         let class_names = classes
             .iter()
@@ -99,6 +81,61 @@ impl From<super::JSSAContext<'_>> for JSSAContext {
     }
 }
 
+/// Converts modules one at a time, as when streaming them. Components are numbered apart from
+/// those of the modules converted before them, like in a whole `JSSAContext`.
+#[derive(Debug)]
+pub struct ModuleConverter {
+    id: i64,
+}
+
+impl Default for ModuleConverter {
+    fn default() -> Self {
+        ModuleConverter { id: 2 }
+    }
+}
+
+impl ModuleConverter {
+    pub fn convert(&mut self, module: &super::ModuleComponent) -> ModuleComponent {
+        let funcs = functions(&module.classes, std::slice::from_ref(module));
+        let methods: Vec<_> = add_ids(&funcs, &mut self.id, |func, id| {
+            MethodComponent::convert_compat(func, id)
+        });
+        let classes: Vec<_> = add_ids(&module.classes, &mut self.id, |class, id| {
+            ClassOrInterfaceComponent::convert_compat(class, id, &methods)
+        })
+        .into_iter()
+        .filter(|class| matches!(class.declaration_type, ContainerType::Class))
+        .collect();
+
+        let module = ModuleComponent::convert_compat(module, self.id, &methods, &classes);
+        self.id += 1;
+        module
+    }
+}
+
+/// Methods of the classes, then their constructors, then the functions of the modules
+fn functions(
+    classes: &[super::ClassOrInterfaceComponent],
+    modules: &[super::ModuleComponent],
+) -> Vec<super::MethodComponent> {
+    let constructors: Vec<_> = classes
+        .iter()
+        .flat_map(|class| class.constructors.clone())
+        .collect();
+
+    let module_functions: Vec<_> = modules
+        .iter()
+        .flat_map(|module| module.component.methods.clone())
+        .collect();
+
+    classes
+        .iter()
+        .flat_map(|class| class.component.methods.clone())
+        .chain(constructors)
+        .chain(module_functions)
+        .collect()
+}
+
 fn add_ids<T, F, R>(components: &[T], id: &mut i64, id_adder: F) -> Vec<R>
 where
     F: Fn(&T, &mut i64) -> R,
@@ -128,6 +165,12 @@ pub struct MethodComponent {
     pub is_abstract: bool,
     #[serde(rename = "final_method")]
     pub is_final: bool,
+    #[serde(rename = "virtual_method")]
+    pub is_virtual: bool,
+    #[serde(rename = "override_method")]
+    pub is_override: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<super::Template>,
     #[serde(rename = "subroutines")]
     pub sub_methods: Vec<MethodComponent>,
     #[serde(rename = "subComponents")]
@@ -171,6 +214,11 @@ impl MethodComponent {
             ..other.component.clone()
         };
 
+        // Sub-methods are numbered before the method declaring them
+        let sub_methods = add_ids(&other.sub_methods, id, |method, id| {
+            MethodComponent::convert_compat(method, id)
+        });
+
         MethodComponent {
             id: *id,
             component,
@@ -181,7 +229,10 @@ impl MethodComponent {
             is_static: other.is_static,
             is_abstract: other.is_abstract,
             is_final: other.is_final,
-            sub_methods: vec![],
+            is_virtual: other.is_virtual,
+            is_override: other.is_override,
+            template: other.template.clone(),
+            sub_methods,
             sub_components,
             annotations, // add to subcomponents as well
             line_count: other.line_count,
@@ -200,6 +251,8 @@ impl MethodComponent {
             && self.is_static == other.is_static
             && self.is_abstract == other.is_abstract
             && self.is_final == other.is_final
+            && self.is_virtual == other.is_virtual
+            && self.is_override == other.is_override
             && self.annotations.len() == other.annotations.len()
             && self.line_count == other.line_count
             && self.line_begin == other.line_begin
@@ -264,6 +317,8 @@ pub struct ModuleComponent {
     #[serde(flatten)]
     pub component: ContainerComponent,
     pub module_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
     #[serde(rename = "moduleStereotype")]
     pub module_stereotype: ModuleStereotype,
     pub class_names: Vec<String>,
@@ -271,6 +326,10 @@ pub struct ModuleComponent {
     pub containers: Vec<ClassOrInterfaceComponent>,
     pub classes: Vec<ClassOrInterfaceComponent>,
     pub interfaces: Vec<ClassOrInterfaceComponent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<ImportStmt>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub macros: Vec<super::MacroDefinition>,
 }
 
 impl ModuleComponent {
@@ -325,6 +384,7 @@ impl ModuleComponent {
         ModuleComponent {
             component: ContainerComponent::convert_compat(&other.component, id, methods, &[], true),
             module_name: other.module_name.clone(),
+            namespace: other.namespace.clone(),
             module_stereotype: other.module_stereotype.clone(),
             class_names,
             interface_names,
@@ -335,6 +395,8 @@ impl ModuleComponent {
                 .collect(),
             classes: class_ids,
             interfaces: interface_ids,
+            imports: other.imports.clone(),
+            macros: other.macros.clone(),
         }
     }
 }
@@ -361,10 +423,16 @@ pub struct ClassOrInterfaceComponent {
     #[serde(flatten)]
     pub component: ContainerComponent,
     pub declaration_type: ContainerType,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub base_classes: Vec<super::BaseClass>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<super::Template>,
     pub annotations: Vec<AnnotationComponent>,
     pub constructors: Vec<MethodComponent>,
     #[serde(rename = "fieldComponents")]
     pub field_components: Vec<FieldComponent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inner_classes: Vec<ClassOrInterfaceComponent>,
 }
 
 impl ClassOrInterfaceComponent {
@@ -401,9 +469,18 @@ impl ClassOrInterfaceComponent {
 
         let annotations: Vec<_> = AnnotationComponent::convert_all(&other.annotations, id);
 
-        // The instance_name should have already been adjusted to
-        // name::ClassComponent or name::InterfaceComponent
+        // Inner classes are numbered before the class declaring them, along with their methods
+        let inner_classes = add_ids(&other.inner_classes, id, |class, id| {
+            let funcs = functions(std::slice::from_ref(class), &[]);
+            let methods: Vec<_> = add_ids(&funcs, id, |func, id| {
+                MethodComponent::convert_compat(func, id)
+            });
+            ClassOrInterfaceComponent::convert_compat(class, id, &methods)
+        });
+
         ClassOrInterfaceComponent {
+            // The instance_name should have already been adjusted to
+            // name::ClassComponent or name::InterfaceComponent
             component: ContainerComponent::convert_compat(
                 &other.component,
                 *id,
@@ -412,6 +489,8 @@ impl ClassOrInterfaceComponent {
                 false,
             ),
             declaration_type: other.declaration_type.clone(),
+            base_classes: other.base_classes.clone(),
+            template: other.template.clone(),
             annotations,
             constructors,
             field_components: other
@@ -432,6 +511,7 @@ impl ClassOrInterfaceComponent {
                     r#type: f.r#type.clone(),
                 })
                 .collect(),
+            inner_classes,
         }
     }
 
@@ -1,6 +1,8 @@
 use super::*;
 use crate::ast::Block;
 use crate::ast::Expr;
+use crate::ast::ImportStmt;
+use derive_new::new;
 use serde::Serialize;
 use source_code_parser_macro::{ChildFields, NodeLanguage};
 
@@ -40,6 +42,8 @@ pub enum ComponentType {
     MethodComponent(MethodComponent),
     ModuleComponent(ModuleComponent),
     FieldComponent(FieldComponent),
+    ImportStmt(ImportStmt),
+    MacroDefinition(MacroDefinition),
 }
 
 #[derive(Debug, Eq, PartialEq, Serialize, Clone, NodeLanguage, ChildFields)]
@@ -59,6 +63,14 @@ pub struct MethodComponent {
     pub is_abstract: bool,
     #[serde(rename = "final_method")]
     pub is_final: bool,
+    /// Whether the method can be overridden by subclasses, as C++ `virtual` methods
+    #[serde(rename = "virtual_method")]
+    pub is_virtual: bool,
+    /// Whether the method is marked as overriding a method of a base class
+    #[serde(rename = "override_method")]
+    pub is_override: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<Template>,
     #[serde(rename = "subroutines")]
     pub sub_methods: Vec<MethodComponent>,
     pub annotations: Vec<AnnotationComponent>,
@@ -98,6 +110,16 @@ pub struct ModuleComponent {
     // containers
     pub classes: Vec<ClassOrInterfaceComponent>,
     pub interfaces: Vec<ClassOrInterfaceComponent>,
+    /// Files or modules imported by the module, like C++ `#include`s
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<ImportStmt>,
+    /// Preprocessor macros defined by the module
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub macros: Vec<MacroDefinition>,
+    /// Qualified name of the namespace declaring the module's contents, for modules lowered from
+    /// a namespace rather than a directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 impl ModuleComponent {
@@ -123,6 +145,9 @@ impl ModuleComponent {
             module_stereotype: ModuleStereotype::Fabricated,
             classes: vec![],
             interfaces: vec![],
+            imports: vec![],
+            macros: vec![],
+            namespace: None,
         }
     }
 
@@ -130,9 +155,50 @@ impl ModuleComponent {
     pub fn merge_into(&mut self, mut other: ModuleComponent) {
         self.classes.append(&mut other.classes);
         self.interfaces.append(&mut other.interfaces);
+        self.imports.append(&mut other.imports);
+        self.macros.append(&mut other.macros);
     }
 }
 
+/// A class inherited from, with the access it is inherited with
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new)]
+pub struct BaseClass {
+    pub name: String,
+    pub accessor: AccessorType,
+}
+
+/// The template a class or method is declared with
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new)]
+pub struct Template {
+    pub parameters: Vec<TemplateParameter>,
+    /// The template specialized, for specializations (`Pool` for `template <> class Pool<int>`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary: Option<String>,
+    /// The arguments the primary template is specialized with
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new)]
+pub struct TemplateParameter {
+    pub name: String,
+    /// `typename`, `class` or `template` for type parameters, or the type of a value parameter
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+    pub is_variadic: bool,
+}
+
+/// A preprocessor macro, as defined with `#define`
+#[derive(Debug, Eq, PartialEq, Serialize, Clone, new)]
+pub struct MacroDefinition {
+    pub name: String,
+    /// Parameters of a function-like macro
+    pub parameters: Option<Vec<String>>,
+    /// Replacement text
+    pub value: String,
+}
+
 #[derive(Debug, Eq, PartialEq, Serialize, Clone, NodeLanguage, ChildFields)]
 pub struct ContainerComponent {
     #[serde(flatten)]
@@ -155,11 +221,21 @@ pub struct ClassOrInterfaceComponent {
     #[serde(flatten)]
     pub component: ContainerComponent,
     pub declaration_type: ContainerType,
+    /// Classes extended and interfaces implemented, like C++ base classes or Java `extends` and
+    /// `implements`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub base_classes: Vec<BaseClass>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<Template>,
     pub annotations: Vec<AnnotationComponent>,
 
     pub constructors: Vec<MethodComponent>,
     #[serde(rename = "fieldComponents")]
     pub field_components: Vec<FieldComponent>,
+    /// Classes declared in this one, such as nested, inner and anonymous classes.
+    /// Local classes are kept in the body of the method declaring them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inner_classes: Vec<ClassOrInterfaceComponent>,
 }
 
 #[derive(Debug, Eq, PartialEq, Serialize, Clone, NodeLanguage, ChildFields)]
@@ -54,6 +54,9 @@ mod tests {
                     is_static: false,
                     is_abstract: false,
                     is_final: false,
+                    is_virtual: false,
+                    is_override: false,
+                    template: None,
                     sub_methods: vec![],
                     annotations: vec![AnnotationComponent {
                         component: ComponentInfo {
@@ -94,6 +97,8 @@ mod tests {
                     line_count: 3,
                 },
                 declaration_type: ContainerType::Class,
+                base_classes: vec![],
+                template: None,
                 annotations: vec![],
                 constructors: vec![],
                 field_components: vec![FieldComponent {
@@ -114,8 +119,12 @@ mod tests {
                     r#type: "String".to_string(),
                     expression: None,
                 }],
+                inner_classes: vec![],
             }],
             interfaces: vec![],
+            imports: vec![],
+            macros: vec![],
+            namespace: None,
         }];
         let _actual = json!(JSSAContext {
             component: ComponentInfo {
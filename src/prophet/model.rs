@@ -66,6 +66,10 @@ pub enum AccessorType {
     Protected,
     #[serde(rename = "DEFAULT")]
     Default,
+    /// Visible only within its file, like the items of a C++ anonymous namespace. Prophet has no
+    /// such accessor, so it is output as private.
+    #[serde(rename = "PRIVATE")]
+    File,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, Serialize, Deserialize, Ordinalize)]
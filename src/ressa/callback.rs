@@ -2,7 +2,9 @@ use once_cell::sync::OnceCell;
 use rune::{runtime::Value, Diagnostics, FromValue, Source, Sources, Vm};
 use std::sync::Arc;
 
-use super::{ContextLocalVariableActions, ContextObjectActions, NodePattern, ParserContext};
+use super::{
+    ContextLocalVariableActions, ContextObjectActions, NodePattern, NodeView, ParserContext,
+};
 
 static EXEC: OnceCell<Executor> = OnceCell::new();
 
@@ -42,6 +44,18 @@ impl Executor {
         module.inst_fn("get_variable", ParserContext::get_variable)?;
         module.inst_fn("clear_variables", ParserContext::clear_variables)?;
 
+        // Register the read-only view of the matched node
+        module.ty::<NodeView>()?;
+        module.inst_fn("kind", NodeView::kind)?;
+        module.inst_fn("name", NodeView::name)?;
+        module.inst_fn("auxiliary", NodeView::auxiliary)?;
+        module.inst_fn("language", NodeView::language)?;
+        module.inst_fn("file", NodeView::file)?;
+        module.inst_fn("line_begin", NodeView::line_begin)?;
+        module.inst_fn("line_end", NodeView::line_end)?;
+        module.inst_fn("children", NodeView::children)?;
+        module.inst_fn("parents", NodeView::parents)?;
+
         // Add more option methods
         module.inst_fn("as_ref", Option::<Value>::as_ref)?;
         module.inst_fn("clone", Option::<Value>::clone)?;
@@ -50,18 +64,21 @@ impl Executor {
         Ok(Executor { executor_ctx })
     }
 
+    /// Run the pattern's callback on the context. A failed callback may have changed the
+    /// context before failing.
     pub fn execute(
         &self,
         pattern: &NodePattern,
-        ctx: ParserContext,
-    ) -> Result<ParserContext, Error> {
+        ctx: &mut ParserContext,
+        node: NodeView,
+    ) -> Result<(), Error> {
         match &pattern.callback {
             Some(callback) => {
                 if callback.trim().is_empty() {
-                    return Ok(ctx);
+                    return Ok(());
                 }
                 let mut sources = Sources::new();
-                let source = format!("pub fn main(ctx) {{ {} Some(ctx) }}", callback);
+                let source = format!("pub fn main(ctx, node) {{ {} Some(ctx) }}", callback);
                 sources.insert(Source::new("callback", source));
 
                 let mut diagnostics = Diagnostics::new();
@@ -76,15 +93,13 @@ impl Executor {
                 );
 
                 let mut vm = Vm::new(runtime, unit);
-                // For some reason we have to pass ownership of the context and retrieve it back from a return value...
-                // This is being done with a wrapper "main" function that calls the callback and returns the context again.
-                let ret_val = vm.execute(&["main"], (ctx,))?.complete()?;
-                let ctx: Option<ParserContext> = FromValue::from_value(ret_val)?;
-                let ctx = ctx.ok_or(Error::MissingObject)?;
-
-                Ok(ctx)
+                // The callback works on the context in place, and returns it again (or `None`
+                // to reject the match) through a wrapper "main" function
+                let ret_val = vm.call(&["main"], (ctx, node))?;
+                let ret_val: Option<Value> = FromValue::from_value(ret_val)?;
+                ret_val.map(|_| ()).ok_or(Error::MissingObject)
             }
-            None => Ok(ctx),
+            None => Ok(()),
         }
     }
 
@@ -102,7 +117,11 @@ impl Executor {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ressa::NodeType, Language};
+    use crate::{
+        ast::{CallExpr, DotExpr, Ident},
+        ressa::{ExplorerContext, NodeType},
+        Language,
+    };
     use std::cell::RefCell;
 
     use super::*;
@@ -129,7 +148,9 @@ mod tests {
         );
         let mut ctx = ParserContext::default();
         // let old = ctx.clone();
-        ctx = Executor::get().execute(&pattern, ctx).unwrap();
+        Executor::get()
+            .execute(&pattern, &mut ctx, NodeView::default())
+            .unwrap();
         // assert_ne!(old, ctx); // TODO fix
         assert_eq!("bar", ctx.get_variable("foo").unwrap())
     }
@@ -158,11 +179,56 @@ mod tests {
             Some(Language::default()),
         );
         let old = ctx.clone();
-        ctx = Executor::get().execute(&pattern, ctx).unwrap();
+        Executor::get()
+            .execute(&pattern, &mut ctx, NodeView::default())
+            .unwrap();
         // assert_eq!(old, ctx); // TODO fix
         assert_eq!(
             old.get_variable("foo").unwrap(),
             ctx.get_variable("foo").unwrap()
         )
     }
+
+    #[test]
+    pub fn read_node_from_script() {
+        let call = CallExpr::new(
+            Box::new(
+                DotExpr::new(
+                    Box::new(Ident::new("repository".into(), Language::Java).into()),
+                    Box::new(Ident::new("save".into(), Language::Java).into()),
+                    Language::Java,
+                )
+                .into(),
+            ),
+            vec![Ident::new("order".into(), Language::Java).into()],
+            Language::Java,
+        );
+        let pattern = NodePattern::new(
+            NodeType::CallExpr,
+            RefCell::new(None),
+            RefCell::new(None),
+            vec![],
+            Some(
+                r#"
+            ctx.make_variable("kind", node.kind());
+            ctx.make_variable("name", node.name());
+            ctx.make_variable("receiver", node.auxiliary().unwrap());
+            ctx.make_variable("arg", node.children()[0].name());
+            "#
+                .into(),
+            ),
+            true,
+            "".into(),
+            None,
+            false,
+            Some(Language::Java),
+        );
+        let node = ExplorerContext::default().describe(&call);
+        let mut ctx = ParserContext::default();
+        Executor::get().execute(&pattern, &mut ctx, node).unwrap();
+        assert_eq!("CallExpr", ctx.get_variable("kind").unwrap());
+        assert_eq!("save", ctx.get_variable("name").unwrap());
+        assert_eq!("repository", ctx.get_variable("receiver").unwrap());
+        assert_eq!("order", ctx.get_variable("arg").unwrap());
+    }
 }
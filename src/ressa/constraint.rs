@@ -0,0 +1,169 @@
+use serde::Deserialize;
+
+use super::{ExplorerContext, Indexable, LaastIndex, NodePattern};
+use crate::Language;
+
+/// A structural constraint on the surroundings of a matched node.
+/// Constraint patterns are always treated as essential, since they describe something that must be found.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Constraint {
+    /// Some node containing the matched node matches the pattern
+    Ancestor(NodePattern),
+
+    /// A node preceding the matched node in its parent, or a descendant of one, matches the pattern
+    SiblingBefore(NodePattern),
+
+    /// A node following the matched node in its parent, or a descendant of one, matches the pattern
+    SiblingAfter(NodePattern),
+
+    /// The wrapped constraint does not hold
+    Not(Box<Constraint>),
+}
+
+impl Constraint {
+    /// Cascade the language to the constraint's pattern, marking it essential
+    pub fn cascade_language(self, parent_lang: Language) -> Constraint {
+        let cascade = |mut pattern: NodePattern| {
+            pattern.essential = true;
+            pattern.cascade_language(parent_lang)
+        };
+        match self {
+            Constraint::Ancestor(pattern) => Constraint::Ancestor(cascade(pattern)),
+            Constraint::SiblingBefore(pattern) => Constraint::SiblingBefore(cascade(pattern)),
+            Constraint::SiblingAfter(pattern) => Constraint::SiblingAfter(cascade(pattern)),
+            Constraint::Not(constraint) => {
+                Constraint::Not(Box::new(constraint.cascade_language(parent_lang)))
+            }
+        }
+    }
+
+    /// Check whether the constraint holds for the provided node. Variables captured while
+    /// checking are written to the context, except for those found under a `Not`.
+    pub fn check(
+        &self,
+        node: &dyn Indexable,
+        ctx: &mut ExplorerContext,
+        index: &LaastIndex,
+    ) -> bool {
+        ctx.constraint_stack += 1;
+        let result = match self {
+            Constraint::Ancestor(pattern) => index
+                .get_ancestors(node)
+                .into_iter()
+                .any(|ancestor| ancestor.match_node(pattern, ctx, index)),
+            Constraint::SiblingBefore(pattern) => match index.get_siblings(node) {
+                Some((before, _)) => explore_any(pattern, before, ctx, index),
+                None => false,
+            },
+            Constraint::SiblingAfter(pattern) => match index.get_siblings(node) {
+                Some((_, after)) => explore_any(pattern, after, ctx, index),
+                None => false,
+            },
+            Constraint::Not(constraint) => {
+                let checkpoint = ctx.checkpoint();
+                let holds = constraint.check(node, ctx, index);
+                ctx.rollback(checkpoint);
+                !holds
+            }
+        };
+        ctx.constraint_stack -= 1;
+        result
+    }
+}
+
+/// Search the provided subtrees for a match of the pattern
+fn explore_any(
+    pattern: &NodePattern,
+    nodes: Vec<&dyn Indexable>,
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool {
+    nodes
+        .into_iter()
+        .any(|node| node.explore(pattern, ctx, index).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ressa::test_util::run_java;
+
+    #[test]
+    fn ancestor_constraint() {
+        let code = r#"
+        class OrderService {
+            @Transactional
+            public void place(Order order) {
+                repository.save(order);
+            }
+
+            public void draft(Order order) {
+                repository.save(order);
+            }
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "CallExpr",
+            "pattern": "save",
+            "essential": true,
+            "subpatterns": [],
+            "language": "Java",
+            "callback": "ctx.save(ctx.get_variable(\"method\").unwrap(), #{});",
+            "constraints": [{
+                "ancestor": {
+                    "identifier": "Method",
+                    "pattern": "#{method}",
+                    "essential": true,
+                    "subpatterns": [{
+                        "identifier": "Annotation",
+                        "pattern": "@Transactional",
+                        "essential": true,
+                        "subpatterns": []
+                    }]
+                }
+            }]
+        }]"##;
+        assert_eq!(vec!["place".to_string()], run_java(code, patterns));
+    }
+
+    #[test]
+    fn negated_sibling_constraint() {
+        let code = r#"
+        class OrderService {
+            public void checked() {
+                Order checkedOrder = repository.find();
+                Objects.requireNonNull(checkedOrder);
+            }
+
+            public void unchecked() {
+                Order uncheckedOrder = repository.find();
+                uncheckedOrder.ship();
+            }
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "DeclStmt",
+            "pattern": "",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [{
+                "identifier": "VarDecl",
+                "pattern": "#{var}",
+                "essential": true,
+                "subpatterns": []
+            }],
+            "callback": "ctx.save(ctx.get_variable(\"var\").unwrap(), #{});",
+            "constraints": [{
+                "not": {
+                    "sibling_after": {
+                        "identifier": "CallExpr",
+                        "pattern": "requireNonNull",
+                        "essential": true,
+                        "subpatterns": []
+                    }
+                }
+            }]
+        }]"##;
+        assert_eq!(vec!["uncheckedOrder".to_string()], run_java(code, patterns));
+    }
+}
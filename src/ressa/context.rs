@@ -4,7 +4,7 @@ use rune::{
 };
 use std::collections::{HashMap, HashSet};
 
-use super::RessaResult;
+use super::{ObjectProvenance, Provenance, RessaProvenance, RessaResult};
 
 /// Special attribute that's value is the name that a tag should resolve to
 const RESOLVES_TO: &str = "???";
@@ -15,6 +15,23 @@ pub struct ParserContext {
     objectlike_data: HashMap<String, Value>,
     variables: HashMap<String, String>,
     transients: HashSet<String>,
+    /// The match whose callback is running, when provenance is being recorded
+    origin: Option<Provenance>,
+    provenance: RessaProvenance,
+    /// Changes since the last commit, oldest first, so failed matches can be rolled back
+    journal: Vec<Change>,
+}
+
+/// A change to the context, holding what it replaced so it can be undone
+#[derive(Debug, Clone)]
+enum Change {
+    Object(String, Option<Value>),
+    /// An attribute set on an object in place, with the value it replaced
+    Attribute(Shared<Object>, String, Option<Value>),
+    Variable(String, Option<String>),
+    /// A name newly marked transient
+    Transient(String),
+    Provenance(String, Option<ObjectProvenance>),
 }
 
 /// Convert the context into the result format
@@ -94,6 +111,94 @@ pub trait ContextLocalVariableActions {
 }
 
 impl ParserContext {
+    /// Attribute objects saved from now on to the provided match, or stop recording provenance
+    pub(crate) fn set_origin(&mut self, origin: Option<Provenance>) {
+        self.origin = origin;
+    }
+
+    /// Mark the current state, to return to with `rollback`
+    pub(crate) fn checkpoint(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Undo every change made since the checkpoint, newest first
+    pub(crate) fn rollback(&mut self, checkpoint: usize) {
+        while self.journal.len() > checkpoint {
+            match self.journal.pop().unwrap() {
+                Change::Object(name, Some(value)) => {
+                    self.objectlike_data.insert(name, value);
+                }
+                Change::Object(name, None) => {
+                    self.objectlike_data.remove(&name);
+                }
+                Change::Attribute(object, name, replaced) => {
+                    if let Ok(mut object) = object.borrow_mut() {
+                        match replaced {
+                            Some(value) => object.insert(name, value),
+                            None => object.remove(&name),
+                        };
+                    }
+                }
+                Change::Variable(name, Some(value)) => {
+                    self.variables.insert(name, value);
+                }
+                Change::Variable(name, None) => {
+                    self.variables.remove(&name);
+                }
+                Change::Transient(name) => {
+                    self.transients.remove(&name);
+                }
+                Change::Provenance(name, Some(provenance)) => {
+                    self.provenance.insert(name, provenance);
+                }
+                Change::Provenance(name, None) => {
+                    self.provenance.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Forget the changes made so far, so they can no longer be rolled back
+    pub(crate) fn commit(&mut self) {
+        self.journal.clear();
+    }
+
+    fn set_object(&mut self, name: String, value: Value) {
+        let replaced = self.objectlike_data.insert(name.clone(), value);
+        self.journal.push(Change::Object(name, replaced));
+    }
+
+    /// Convert the context into the result format, along with the provenance of each object
+    pub(crate) fn into_result_with_provenance(mut self) -> (RessaResult, RessaProvenance) {
+        let mut provenance = std::mem::take(&mut self.provenance);
+        let result = RessaResult::from(self);
+        provenance.retain(|name, _| result.contains_key(name));
+        (result, provenance)
+    }
+
+    fn record_provenance(&mut self, name: &str, value: &Value) {
+        let origin = match &self.origin {
+            Some(origin) => origin,
+            None => return,
+        };
+        let replaced = self.provenance.get(name).cloned();
+        self.journal.push(Change::Provenance(name.into(), replaced));
+        let entry = self.provenance.entry(name.into()).or_default();
+        if !entry.saved_by.contains(origin) {
+            entry.saved_by.push(origin.clone());
+        }
+        if let Value::Object(obj) = value {
+            if let Ok(obj) = obj.borrow_ref() {
+                for attribute in obj.keys() {
+                    entry
+                        .attributes
+                        .entry(attribute.clone())
+                        .or_insert_with(|| origin.clone());
+                }
+            }
+        }
+    }
+
     fn do_make_attribute(&mut self, obj_name: &str, attr_name: &str, attr_type: Option<String>) {
         // If a reference to a non-existant object, create it
         if !self.objectlike_data.contains_key(obj_name) {
@@ -104,15 +209,18 @@ impl ParserContext {
             self.make_object(obj_name);
         }
 
-        // Insert
-        let vars = self.objectlike_data.get_mut(obj_name).unwrap();
-        if let Value::Object(ref mut vars) = vars {
-            let mut vars = vars.borrow_mut().unwrap();
-            if let Some(attr_type) = attr_type {
-                vars.insert(attr_name.into(), Value::from(attr_type));
-            } else {
-                vars.insert(attr_name.into(), Value::from(Shared::new(None)));
-            }
+        // Insert in place, so handles to the object see it, recording what it replaced
+        if let Some(Value::Object(object)) = self.objectlike_data.get(obj_name) {
+            let value = match attr_type {
+                Some(attr_type) => Value::from(attr_type),
+                None => Value::from(Shared::new(None)),
+            };
+            let replaced = object.borrow_mut().unwrap().insert(attr_name.into(), value);
+            self.journal.push(Change::Attribute(
+                object.clone(),
+                attr_name.into(),
+                replaced,
+            ));
         }
     }
 
@@ -120,7 +228,7 @@ impl ParserContext {
         let obj_name: String = name.into();
         if !self.objectlike_data.contains_key(&obj_name) {
             // tracing::info!("Making: {}", obj_name);
-            (&mut self.objectlike_data).insert(obj_name, Object::new().into());
+            self.set_object(obj_name, Object::new().into());
         }
     }
 }
@@ -128,7 +236,9 @@ impl ParserContext {
 impl ContextObjectActions for ParserContext {
     fn save(&mut self, name: &str, value: Value) -> &Value {
         // tracing::info!("Saving {}: {:?}", name, new_obj);
-        self.objectlike_data.insert(self.resolve_tag(name), value);
+        let resolved = self.resolve_tag(name);
+        self.record_provenance(&resolved, &value);
+        self.set_object(resolved, value);
         self.get(name).unwrap()
     }
 
@@ -152,7 +262,10 @@ impl ContextObjectActions for ParserContext {
     fn make_transient(&mut self, name: &str) {
         // tracing::info!("Making transient {}", name);
         self.make_object(name);
-        self.transients.insert(self.resolve_tag(name));
+        let resolved = self.resolve_tag(name);
+        if self.transients.insert(resolved.clone()) {
+            self.journal.push(Change::Transient(resolved));
+        }
     }
 
     fn resolve_tag(&self, name: &str) -> String {
@@ -182,7 +295,8 @@ impl ContextLocalVariableActions for ParserContext {
         //     overwritten, val, name
         // );
         // }
-        self.variables.insert(name.into(), val.into());
+        let replaced = self.variables.insert(name.into(), val.into());
+        self.journal.push(Change::Variable(name.into(), replaced));
     }
 
     fn get_variable(&self, name: &str) -> Option<String> {
@@ -192,6 +306,84 @@ impl ContextLocalVariableActions for ParserContext {
     }
 
     fn clear_variables(&mut self) {
-        self.variables.clear();
+        for (name, value) in self.variables.drain() {
+            self.journal.push(Change::Variable(name, Some(value)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rune::runtime::{Object, Shared, Value};
+
+    use super::{ContextLocalVariableActions, ContextObjectActions, ParserContext, RESOLVES_TO};
+    use crate::ressa::test_util::run_java;
+
+    #[test]
+    fn rollback_undoes_changes() {
+        let mut ctx = ParserContext::default();
+        ctx.save("order", Value::Integer(1));
+        ctx.make_variable("op", "save");
+        let checkpoint = ctx.checkpoint();
+
+        ctx.save("order", Value::Integer(2));
+        ctx.save("payment", Value::Integer(3));
+        ctx.make_tag("purchase", "order");
+        ctx.make_transient("scratch");
+        ctx.make_variable("op", "delete");
+        ctx.clear_variables();
+
+        ctx.rollback(checkpoint);
+        assert_eq!(checkpoint, ctx.journal.len());
+        assert_eq!(
+            Some(1),
+            ctx.get("order").and_then(|v| v.clone().into_integer().ok())
+        );
+        assert!(ctx.get("payment").is_none());
+        assert_eq!("purchase", ctx.resolve_tag("purchase"));
+        assert!(ctx.transients.is_empty());
+        assert_eq!(Some("save".to_string()), ctx.get_variable("op"));
+    }
+
+    #[test]
+    fn attributes_are_set_in_place() {
+        let mut ctx = ParserContext::default();
+        ctx.save("purchase", Value::from(Shared::new(Object::new())));
+        let handle = ctx.get("purchase").unwrap().clone().into_object().unwrap();
+        let checkpoint = ctx.checkpoint();
+
+        ctx.make_tag("purchase", "order");
+        assert!(handle.borrow_ref().unwrap().contains_key(RESOLVES_TO));
+        assert_eq!("order", ctx.resolve_tag("purchase"));
+
+        ctx.rollback(checkpoint);
+        assert!(!handle.borrow_ref().unwrap().contains_key(RESOLVES_TO));
+    }
+
+    #[test]
+    fn failed_callback_is_rolled_back() {
+        let code = r#"
+        class OrderService {
+            public void place(Order order) {
+                repository.save(order);
+            }
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "Method",
+            "pattern": "#{method}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "ctx.save(\"partial\", #{}); panic(\"rejected\");"
+        }, {
+            "identifier": "Method",
+            "pattern": "#{method}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "ctx.save(\"complete\", #{});"
+        }]"##;
+        assert_eq!(vec!["complete"], run_java(code, patterns));
     }
 }
@@ -1,5 +1,7 @@
 /// WARNING: HERE THERE BE MACROS
-use super::{ressa_node_parse, ExplorerContext, IntoRessaNode, NodePattern, NodePatternParser};
+use super::{
+    ressa_node_parse, ExplorerContext, IntoNodeView, IntoRessaNode, NodePattern, NodePatternParser,
+};
 use super::{Indexable, LaastIndex};
 use crate::ast::*;
 use crate::prophet::*;
@@ -16,6 +18,16 @@ pub trait RessaNodeExplorer {
         ctx: &mut ExplorerContext,
         index: &LaastIndex,
     ) -> Option<()>;
+
+    /// Attempt to match the pattern against this node alone, without searching its descendants
+    fn match_node(
+        &self,
+        _pattern: &NodePattern,
+        _ctx: &mut ExplorerContext,
+        _index: &LaastIndex,
+    ) -> bool {
+        false
+    }
 }
 
 /// Generate a default explore implementation that delegates to the child fields (blanket implementation caused problems)
@@ -49,6 +61,64 @@ macro_rules! ressa_dispatch_match_impl {
                 ) -> Option<()> {
                     crate::ressa::explorer::explore_match(self, pattern, ctx, index)
                 }
+
+                fn match_node(
+                    &self,
+                    pattern: &NodePattern,
+                    ctx: &mut ExplorerContext,
+                    index: &LaastIndex
+                ) -> bool {
+                    crate::ressa::explorer::match_node(self, pattern, ctx, index)
+                }
+            }
+        )*
+    };
+}
+
+/// Generate a delegating explore implementation that records the node as an enclosing scope while it is explored
+macro_rules! ressa_dispatch_scoped_impl {
+    ( $( $struct_name:ty ),+ ) => {
+        $(
+            impl RessaNodeExplorer for $struct_name {
+                fn explore(
+                    &self,
+                    pattern: &NodePattern,
+                    ctx: &mut ExplorerContext,
+                    index: &LaastIndex
+                ) -> Option<()> {
+                    crate::ressa::explorer::explore_scoped(self, ctx, |ctx| {
+                        crate::ressa::explorer::explore(self, pattern, ctx, index)
+                    })
+                }
+            }
+        )*
+    };
+}
+
+/// Generate a matching explore implementation that records the node as an enclosing scope while it is explored
+macro_rules! ressa_dispatch_scoped_match_impl {
+    ( $( $struct_name:ty ),+ ) => {
+        $(
+            impl RessaNodeExplorer for $struct_name {
+                fn explore(
+                    &self,
+                    pattern: &NodePattern,
+                    ctx: &mut ExplorerContext,
+                    index: &LaastIndex
+                ) -> Option<()> {
+                    crate::ressa::explorer::explore_scoped(self, ctx, |ctx| {
+                        crate::ressa::explorer::explore_match(self, pattern, ctx, index)
+                    })
+                }
+
+                fn match_node(
+                    &self,
+                    pattern: &NodePattern,
+                    ctx: &mut ExplorerContext,
+                    index: &LaastIndex
+                ) -> bool {
+                    crate::ressa::explorer::match_node(self, pattern, ctx, index)
+                }
             }
         )*
     };
@@ -74,7 +144,13 @@ ressa_dispatch_delegate_impl!(
     ContinueStmt,
     ThrowStmt,
     LabelStmt,
+    GotoStmt,
+    AssertStmt,
+    SynchronizedStmt,
+    ClassDeclStmt,
     LambdaExpr,
+    InstanceOfExpr,
+    MethodRefExpr,
     CaseExpr,
     ExprStmt,
     CatchStmt,
@@ -84,7 +160,6 @@ ressa_dispatch_delegate_impl!(
     UnaryExpr,
     ParenExpr,
     DotExpr,
-    ModuleComponent,
     AssignExpr,
     InitListExpr,
     SwitchExpr,
@@ -99,8 +174,6 @@ ressa_dispatch_delegate_impl!(
 ressa_dispatch_match_impl!(
     Ident,
     Literal,
-    ClassOrInterfaceComponent,
-    MethodComponent,
     MethodParamComponent,
     FieldComponent,
     DeclStmt,
@@ -110,6 +183,22 @@ ressa_dispatch_match_impl!(
     AnnotationValuePair,
     BinaryExpr
 );
+ressa_dispatch_scoped_impl!(ModuleComponent);
+ressa_dispatch_scoped_match_impl!(ClassOrInterfaceComponent, MethodComponent);
+
+/// Explore a node, recording it as an enclosing scope for the duration
+pub fn explore_scoped<T, F>(source: &T, ctx: &mut ExplorerContext, explore_fn: F) -> Option<()>
+where
+    T: IntoNodeView,
+    F: FnOnce(&mut ExplorerContext) -> Option<()>,
+{
+    let entered = ctx.enter_scope(source);
+    let result = explore_fn(ctx);
+    if entered {
+        ctx.exit_scope();
+    }
+    result
+}
 
 pub fn explore<T>(
     source: &T,
@@ -136,7 +225,7 @@ pub fn explore_match<T>(
     index: &LaastIndex,
 ) -> Option<()>
 where
-    T: Indexable + IntoRessaNode + NodePatternParser,
+    T: Indexable + IntoRessaNode + IntoNodeView + NodePatternParser,
 {
     // Check languages to validate remotely reasonable
     let lang_match = pattern.language_matches(source);
@@ -156,10 +245,23 @@ where
     choose_exit(pattern.essential, matched || found)
 }
 
+pub fn match_node<T>(
+    source: &T,
+    pattern: &NodePattern,
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool
+where
+    T: Indexable + IntoRessaNode + IntoNodeView + NodePatternParser,
+{
+    pattern.language_matches(source)
+        && pattern.matches(source)
+        && ressa_node_parse(pattern, source, ctx, index).is_some()
+}
+
 #[cfg(test)]
 mod tests {
     use std::cell::RefCell;
-    use std::collections::HashMap;
 
     use crate::ressa::NodeType;
 
@@ -185,7 +287,7 @@ mod tests {
             false,
             Some(Language::default()),
         );
-        let index = LaastIndex::new(HashMap::new(), HashMap::new());
+        let index = LaastIndex::default();
         tracing::warn!("hello?");
         c.explore(&np, &mut ExplorerContext::default(), &index);
     }
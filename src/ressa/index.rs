@@ -2,82 +2,244 @@ use crate::ast::NodeLanguage;
 use crate::ressa::explorer::RessaNodeExplorer;
 use crate::Language;
 use bitmaps::Bitmap;
-use derive_new::new;
-use std::collections::HashMap;
-use std::hash::Hash;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
 use std::ops::BitOrAssign;
 
-use super::NodePattern;
-
 /// Single indexable reference
 pub type IndexableEntry<'a> = &'a dyn Indexable;
 
-/// Maps references to IndexableEntry to a hash table key
-#[derive(Hash, PartialEq, Eq)]
-pub struct IndexableKey {
-    wrapped: String,
-}
-impl IndexableKey {
-    fn new(entry: IndexableEntry<'_>) -> IndexableKey {
-        IndexableKey {
-            wrapped: format!("{:p}", entry),
-        }
-    }
+/// Identifier of a LAAST node within an index: its position in a depth-first, pre-order walk of
+/// the modules. Ids are stable for a given LAAST, so they can be stored and reused.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+/// The data of a `LaastIndex`, independent of the LAAST it was built from, so it can be stored
+/// and later attached to the same LAAST again
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexData {
+    /// Index of language to root LAAST nodes (largest subtrees containing a given language)
+    language_index: HashMap<Language, Vec<NodeId>>,
+
+    /// Languages of each node's subtree, by node id
+    ast_languages: Vec<LanguageSet>,
+
+    /// Node containing each node, by node id
+    parents: Vec<Option<NodeId>>,
+
+    /// Hash of the shape and languages of the LAAST the data was computed from
+    #[serde(default)]
+    fingerprint: u64,
 }
 
 /// Structure used to index the LAAST
-#[derive(new)]
+#[derive(Default)]
 pub struct LaastIndex<'a> {
-    /// Index of language to root LAAST node (largest subtrees containing a given language)
-    language_index: HashMap<Language, Vec<IndexableEntry<'a>>>,
+    data: Cow<'a, IndexData>,
+
+    /// Nodes, by node id
+    nodes: Vec<IndexableEntry<'a>>,
 
-    /// Reverse index from AST to language set, adding knowledge of subnode languages to a LAAST node
-    ast_languages: HashMap<IndexableKey, LanguageSet>,
+    /// Reverse lookup from a node to its id
+    ids: HashMap<(usize, &'static str), NodeId>,
 }
 impl<'a> LaastIndex<'a> {
-    /// Get all nodes indexed for a language
-    pub fn get_roots(&self, language: Language) -> Option<&Vec<IndexableEntry<'a>>> {
-        self.language_index.get(&language)
+    /// Index the provided modules
+    pub fn build(modules: Vec<IndexableEntry<'a>>) -> LaastIndex<'a> {
+        let mut index = LaastIndex::default();
+        index.data.to_mut().fingerprint = FNV_OFFSET;
+        for module in modules {
+            index.index(Language::Unknown, module, None, LanguageSet::new());
+        }
+        index
+    }
+
+    /// Attach previously computed index data to the LAAST it was computed from, without
+    /// recomputing it. Returns `None` if the LAAST has a different shape or languages.
+    pub fn attach(data: &'a IndexData, modules: Vec<IndexableEntry<'a>>) -> Option<LaastIndex<'a>> {
+        let mut index = LaastIndex {
+            data: Cow::Borrowed(data),
+            ..Default::default()
+        };
+        let mut hash = FNV_OFFSET;
+        let mut stack = modules;
+        stack.reverse();
+        while let Some(node) = stack.pop() {
+            index.assign_id(node);
+            let children = node.get_children();
+            hash = fingerprint(hash, node, children.len());
+            stack.extend(children.into_iter().rev());
+        }
+        if index.nodes.len() == index.data.parents.len() && hash == index.data.fingerprint {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Number of indexed nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Data of the index, to attach to the same LAAST later
+    pub fn data(&self) -> &IndexData {
+        &self.data
+    }
+
+    /// Get the roots indexed for any of the languages, each once and in the order they were
+    /// indexed
+    pub fn get_roots(&self, languages: &[Language]) -> Vec<IndexableEntry<'a>> {
+        let ids: BTreeSet<NodeId> = languages
+            .iter()
+            .filter_map(|language| self.data.language_index.get(language))
+            .flatten()
+            .copied()
+            .collect();
+        ids.into_iter().map(|id| self.get_node(id)).collect()
     }
 
-    /// Retrieve whether a given language exists in the subtree of the provided node
+    /// Retrieve whether a given language exists in the subtree of the provided node. Nodes
+    /// outside the index may contain any language.
     pub fn language_in_subtree(&self, language: Language, subtree: IndexableEntry) -> bool {
         // Edge case: any language requested
         if language == Language::Unknown {
             return true;
         }
 
-        // Handle main case
-        // println!("Check index...");
-        match self.ast_languages.get(&IndexableKey::new(subtree)) {
-            Some(descendent_languages) => descendent_languages.contains(language),
-            None => panic!("Unknown subtree provided"),
+        match self.node_id(subtree) {
+            Some(id) => self.data.ast_languages[id.0 as usize].contains(language),
+            None => true,
+        }
+    }
+
+    /// Retrieve the id of the provided node, if it was indexed
+    pub fn node_id(&self, node: IndexableEntry) -> Option<NodeId> {
+        self.ids.get(&node.node_key()).copied()
+    }
+
+    /// Retrieve the node with the provided id
+    pub fn get_node(&self, id: NodeId) -> IndexableEntry<'a> {
+        self.nodes[id.0 as usize]
+    }
+
+    /// Retrieve the node directly containing the provided node, if it was indexed
+    pub fn get_parent(&self, node: IndexableEntry) -> Option<IndexableEntry<'a>> {
+        let parent = self.data.parents[self.node_id(node)?.0 as usize]?;
+        Some(self.get_node(parent))
+    }
+
+    /// Retrieve all nodes containing the provided node, innermost first
+    pub fn get_ancestors(&self, node: IndexableEntry) -> Vec<IndexableEntry<'a>> {
+        let mut ancestors = vec![];
+        let mut current = self.get_parent(node);
+        while let Some(parent) = current {
+            ancestors.push(parent);
+            current = self.get_parent(parent);
         }
+        ancestors
+    }
+
+    /// Retrieve the children of the provided node's parent that come before and after it
+    pub fn get_siblings(
+        &self,
+        node: IndexableEntry,
+    ) -> Option<(Vec<IndexableEntry<'a>>, Vec<IndexableEntry<'a>>)> {
+        let mut siblings = self.get_parent(node)?.get_children();
+        let key = node.node_key();
+        let position = siblings
+            .iter()
+            .position(|sibling| sibling.node_key() == key)?;
+        let after = siblings.split_off(position + 1);
+        siblings.pop();
+        Some((siblings, after))
+    }
+
+    /// Give the node the next id
+    fn assign_id(&mut self, node: IndexableEntry<'a>) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(node);
+        self.ids.insert(node.node_key(), id);
+        id
     }
 
-    /// Indexes the given node, if its language is allowed to be indexed on
-    fn add_if_valid(&mut self, node: IndexableEntry<'a>) {
-        // Index under specific language
-        if let Some(index) = self.language_index.get_mut(&node.get_language()) {
-            // println!("{:#?}", node.get_language());
-            index.push(node);
+    /// Run the indexing procedure over the given node, returning the languages in its subtree
+    fn index(
+        &mut self,
+        mut current_lang: Language,
+        current: IndexableEntry<'a>,
+        parent: Option<NodeId>,
+        mut curr_langs: LanguageSet,
+    ) -> LanguageSet {
+        let id = self.assign_id(current);
+        let children = current.get_children();
+        let data = self.data.to_mut();
+        data.parents.push(parent);
+        data.ast_languages.push(LanguageSet::new());
+        data.fingerprint = fingerprint(data.fingerprint, current, children.len());
+
+        // If language changed, update data
+        let new_lang = current.get_language();
+        if new_lang != current_lang {
+            // If it's a new language in this subtree, create an index entry
+            if !curr_langs.contains(new_lang) {
+                self.add_root(id, new_lang);
+            }
+
+            // Update current language data
+            curr_langs.set(new_lang);
+            current_lang = new_lang;
         }
 
-        // Index under catchall
-        if let Some(index) = self.language_index.get_mut(&Language::Unknown) {
-            // println!("Catchall");
-            index.push(node);
+        // Visit decendents and retrieve descendent languages
+        let mut my_set = LanguageSet::new();
+        my_set.set(current_lang);
+        for node in children {
+            my_set |= self.index(current_lang, node, Some(id), curr_langs);
         }
+
+        // Record and return node's languages
+        self.data.to_mut().ast_languages[id.0 as usize] = my_set;
+        my_set
     }
 
-    /// Indexes the given node, if its language is indexed on
-    fn record_subtree_langs(&mut self, node: IndexableEntry<'a>, langs: LanguageSet) {
-        self.ast_languages.insert(IndexableKey::new(node), langs);
+    /// Indexes the given node as a root of its language, and of the catchall
+    fn add_root(&mut self, id: NodeId, language: Language) {
+        let language_index = &mut self.data.to_mut().language_index;
+        language_index.entry(language).or_default().push(id);
+        if language != Language::Unknown {
+            language_index
+                .entry(Language::Unknown)
+                .or_default()
+                .push(id);
+        }
     }
 }
 
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Mix a node into the (FNV-1a) fingerprint of a LAAST. Mixing the nodes in pre-order with their
+/// number of children captures the shape of the tree, so two LAASTs share a fingerprint when
+/// their nodes have the same types, languages and parents, which is all the index records.
+fn fingerprint(hash: u64, node: IndexableEntry, children: usize) -> u64 {
+    let (_, kind) = node.node_key();
+    kind.bytes()
+        .chain((node.get_language().ordinal() as u32).to_le_bytes())
+        .chain((children as u32).to_le_bytes())
+        .fold(hash, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
 /// Describe a specialization of the Bitmap type for indexing Languages
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
 pub struct LanguageSet(Bitmap<32>);
 impl LanguageSet {
     fn new() -> LanguageSet {
@@ -100,75 +262,135 @@ impl BitOrAssign for LanguageSet {
         self.0.bitor_assign(rhs.0)
     }
 }
-
-/// Compute the languages to index over
-pub fn compute_index_languages<'a>(
-    patterns: &[NodePattern],
-    nodes: Vec<IndexableEntry<'a>>,
-) -> LaastIndex<'a> {
-    // Compute all languages to index on
-    let mut indices = HashMap::new();
-    for pattern in patterns.iter() {
-        let language = pattern.get_language();
-        indices.entry(language).or_insert_with(Vec::new);
+impl From<u32> for LanguageSet {
+    fn from(bits: u32) -> Self {
+        LanguageSet(Bitmap::from_value(bits))
     }
-
-    // Generate indices
-    let mut indices = LaastIndex::new(indices, HashMap::new());
-    for node in nodes {
-        // println!("Node: {:#?}", node.get_language());
-        index(Language::Unknown, node, LanguageSet::new(), &mut indices);
-    }
-
-    indices
 }
-
-/// Run the indexing procedure over the given node
-fn index<'a>(
-    mut current_lang: Language,
-    current: IndexableEntry<'a>,
-    mut curr_langs: LanguageSet,
-    indices: &mut LaastIndex<'a>,
-) -> LanguageSet {
-    // If language changed, update data
-    let new_lang = current.get_language();
-    if new_lang != current_lang {
-        // If it's a new language in this subtree, try to create an index entry
-        if !curr_langs.contains(new_lang) {
-            // println!("Index {:#?}", new_lang);
-            indices.add_if_valid(current);
-        }
-
-        // Update current language data
-        curr_langs.set(new_lang);
-        current_lang = new_lang;
-    }
-
-    // Visit decendents and retrieve descendent languages
-    let mut my_set = LanguageSet::new();
-    my_set.set(current_lang);
-    for node in current.get_children() {
-        my_set |= index(current_lang, node, curr_langs, indices);
+impl From<LanguageSet> for u32 {
+    fn from(set: LanguageSet) -> Self {
+        set.0.into_value()
     }
-
-    // Record and return node's languages
-    indices.record_subtree_langs(current, my_set);
-    my_set
 }
 
-pub trait Indexable: RessaNodeExplorer + NodeLanguage + ChildFields + std::fmt::Debug {
+pub trait Indexable:
+    RessaNodeExplorer + NodeLanguage + ChildFields + std::fmt::Debug + Sync
+{
     fn get_children(&self) -> Vec<&dyn Indexable>;
 }
 
 pub trait ChildFields {
     fn get_fields(&self) -> Vec<Vec<&dyn Indexable>>;
+
+    /// Address and kind of the underlying node, looking through enum wrappers so that a wrapped
+    /// node and its wrapper share a key. The kind, the name of the node's type as declared,
+    /// distinguishes a node from a child stored at the same address, and is stable across
+    /// compilers so that it can be fingerprinted.
+    fn node_key(&self) -> (usize, &'static str);
 }
 
 impl<T> Indexable for T
 where
-    T: RessaNodeExplorer + ChildFields + NodeLanguage + std::fmt::Debug,
+    T: RessaNodeExplorer + ChildFields + NodeLanguage + std::fmt::Debug + Sync,
 {
     fn get_children(&self) -> Vec<&dyn Indexable> {
         self.get_fields().into_iter().flatten().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexData, Indexable, LaastIndex, NodeId};
+    use crate::ast::Ident;
+    use crate::ressa::{index_modules, run_ressa_parse_with_index, NodePattern};
+    use crate::test_util::{parse_files, parse_java};
+    use crate::Language;
+
+    #[test]
+    fn fingerprints_are_stable() {
+        // Fingerprints are stored along with indexes, so they must not depend on the compiler
+        let modules = parse_java("class Empty {}");
+        assert_eq!(
+            9707600099772550868,
+            index_modules(&modules).data().fingerprint
+        );
+    }
+
+    #[test]
+    fn gets_roots_of_several_languages() {
+        let modules = parse_files(&[
+            ("OrderService.java", "class OrderService {}"),
+            ("orders.cpp", "void place() {}"),
+        ]);
+        let index = index_modules(&modules);
+        let ids = |languages: &[Language]| {
+            index
+                .get_roots(languages)
+                .into_iter()
+                .map(|root| index.node_id(root).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let java = ids(&[Language::Java]);
+        let cpp = ids(&[Language::Cpp]);
+        assert_eq!(1, java.len());
+        assert_eq!(1, cpp.len());
+        assert!(ids(&[Language::Go]).is_empty());
+
+        // Roots found for several of the languages are only listed once
+        let mut both = [java, cpp].concat();
+        both.sort();
+        assert_eq!(both, ids(&[Language::Cpp, Language::Java]));
+        assert_eq!(both, ids(&[Language::Java, Language::Unknown]));
+    }
+
+    #[test]
+    fn reattaches_stored_index() {
+        let code = r#"
+        class OrderService {
+            public void place(Order order) {
+                repository.save(order);
+            }
+        }
+        "#;
+        let modules = parse_java(code);
+        let stored = serde_json::to_string(index_modules(&modules).data()).unwrap();
+
+        let data: IndexData = serde_json::from_str(&stored).unwrap();
+        let nodes = modules.iter().map(|m| m as &dyn Indexable).collect();
+        let index = LaastIndex::attach(&data, nodes).unwrap();
+        let roots = index.get_roots(&[Language::Java]);
+        assert_eq!(1, roots.len());
+        assert_eq!(Some(NodeId(0)), index.node_id(roots[0]));
+        assert!(index.language_in_subtree(Language::Java, roots[0]));
+        assert!(!index.language_in_subtree(Language::Cpp, roots[0]));
+
+        // Nodes outside the index are searched rather than rejected
+        let stray = Ident::new("stray".into(), Language::Java);
+        assert!(index.language_in_subtree(Language::Cpp, &stray));
+
+        let patterns = r##"[{
+            "identifier": "CallExpr",
+            "pattern": "#{op}(save)",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "ctx.save(ctx.get_variable(\"op\").unwrap(), #{});"
+        }]"##;
+        let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+        let report = run_ressa_parse_with_index(&modules, &index, patterns, Default::default());
+        assert!(report.result.contains_key("save"));
+
+        // A different LAAST cannot take the index
+        let other = parse_java("class Empty {}");
+        let data: IndexData = serde_json::from_str(&stored).unwrap();
+        let nodes = other.iter().map(|m| m as &dyn Indexable).collect();
+        assert!(LaastIndex::attach(&data, nodes).is_none());
+
+        // Even when it has as many nodes
+        let mut other = modules.clone();
+        other[0].classes[0].component.methods[0].component.language = Language::Cpp;
+        let nodes = other.iter().map(|m| m as &dyn Indexable).collect();
+        assert!(LaastIndex::attach(&data, nodes).is_none());
+    }
+}
@@ -18,7 +18,32 @@ pub mod index;
 pub use index::*;
 
 pub mod result;
-pub use result::*;
+// The result module's `Error` is left out, as the callback module has one too
+pub use result::{
+    coerce, coerce_primitive, extract, extract_object, extract_primitive, extract_vec,
+    from_ressa_result, from_value, PlainValue, RessaResult,
+};
+
+mod node_view;
+pub use node_view::*;
+
+mod constraint;
+pub use constraint::*;
+
+mod provenance;
+pub use provenance::*;
+
+mod trace;
+pub use trace::*;
+
+mod parallel;
+pub use parallel::*;
+
+#[cfg(test)]
+mod test_util;
+
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, rc::Rc};
 
 use crate::{ast::NodeLanguage, Language, ModuleComponent};
 
@@ -27,44 +52,198 @@ use crate::{ast::NodeLanguage, Language, ModuleComponent};
 #[derive(Default, Debug, Clone)]
 pub struct ExplorerContext {
     pub parser: ParserContext,
-    pub constraint_stack: i32, // TODO implement
+    /// Depth of nested constraint checks the search is currently in
+    pub constraint_stack: i32,
     pub frame_number: i32,
+    /// Index of the root pattern currently being applied
+    pub root_pattern: usize,
+    /// Whether callbacks should record which match saved each object
+    pub record_provenance: bool,
+    /// Enclosing modules, classes and methods of the node being explored, outermost first
+    scopes: Vec<(usize, NodeView)>,
+    /// Events of a traced run. Shared between transactions, so failed matches are kept.
+    trace: Option<Rc<RefCell<RessaTrace>>>,
+}
+
+impl ExplorerContext {
+    /// Record a node as enclosing everything explored until the matching `exit_scope`.
+    /// Returns false (and records nothing) if the node is already the innermost scope.
+    pub(crate) fn enter_scope<N: IntoNodeView>(&mut self, node: &N) -> bool {
+        let address = node as *const N as usize;
+        if matches!(self.scopes.last(), Some((top, _)) if *top == address) {
+            return false;
+        }
+        self.scopes.push((address, node.into_node_view()));
+        true
+    }
+
+    pub(crate) fn exit_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Mark the current state, to return to with `rollback` if a match fails
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            journal: self.parser.checkpoint(),
+            scopes: self.scopes.len(),
+        }
+    }
+
+    /// Undo everything written to the context since the checkpoint
+    pub(crate) fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.parser.rollback(checkpoint.journal);
+        self.scopes.truncate(checkpoint.scopes);
+    }
+
+    /// Start applying the root pattern with the given index
+    fn begin_root_pattern(&mut self, root_pattern: usize) {
+        self.root_pattern = root_pattern;
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().patterns.push(PatternTrace {
+                pattern: root_pattern,
+                events: vec![],
+            });
+        }
+    }
+
+    /// Record an event against the root pattern being applied, if the run is being traced
+    pub(crate) fn trace<F: FnOnce() -> TraceEvent>(&self, event: F) {
+        if let Some(trace) = &self.trace {
+            if let Some(pattern) = trace.borrow_mut().patterns.last_mut() {
+                pattern.events.push(event());
+            }
+        }
+    }
+
+    /// Describe a node for a callback, including its enclosing scopes (excluding itself)
+    pub fn describe<N: IntoNodeView>(&self, node: &N) -> NodeView {
+        let address = node as *const N as usize;
+        let parents = self
+            .scopes
+            .iter()
+            .filter(|(scope, _)| *scope != address)
+            .map(|(_, view)| view.clone())
+            .collect();
+        node.into_node_view().with_parents(parents)
+    }
+}
+
+/// State of an `ExplorerContext` that can be returned to
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checkpoint {
+    journal: usize,
+    scopes: usize,
 }
 
 /// Run the user-defined parsers, in the order they were defined, on our AST
 pub fn run_ressa_parse(ast: &mut Vec<ModuleComponent>, ressas: Vec<NodePattern>) -> RessaResult {
-    // Add all inferred languages (ReSSA languages which are not specified and assumed to be the same as the parent's)
-    let ressas = ressas
+    // Clean and return context
+    explore_ressas(ast, &index_modules(ast), ressas, ExplorerContext::default())
+        .parser
+        .into()
+}
+
+/// Index the modules for a ReSSA run. The index can be reused by later runs on the same modules
+/// with `run_ressa_parse_with_index`.
+pub fn index_modules(ast: &[ModuleComponent]) -> LaastIndex<'_> {
+    LaastIndex::build(ast.iter().map(|module| module as &dyn Indexable).collect())
+}
+
+/// Extra reporting to produce alongside the result of a ReSSA run
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct RessaOptions {
+    /// Record which match (root pattern, node, file and lines) saved each object and attribute
+    #[serde(default)]
+    pub provenance: bool,
+
+    /// Record how each root pattern was applied: nodes considered, failed regexes, aborted
+    /// subpatterns and failed callbacks
+    #[serde(default)]
+    pub trace: bool,
+}
+
+/// Result of a ReSSA run, with any extra reporting requested through `RessaOptions`
+#[derive(Debug, Default, Serialize)]
+pub struct RessaReport {
+    pub result: RessaResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<RessaProvenance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<RessaTrace>,
+}
+
+/// Run the user-defined parsers like `run_ressa_parse`, producing the requested extra reporting
+pub fn run_ressa_parse_with_options(
+    ast: &[ModuleComponent],
+    ressas: Vec<NodePattern>,
+    options: RessaOptions,
+) -> RessaReport {
+    run_ressa_parse_with_index(ast, &index_modules(ast), ressas, options)
+}
+
+/// Run the user-defined parsers like `run_ressa_parse_with_options`, using an index of the
+/// modules built by `index_modules` (or attached from its stored data)
+pub fn run_ressa_parse_with_index(
+    ast: &[ModuleComponent],
+    index: &LaastIndex,
+    ressas: Vec<NodePattern>,
+    options: RessaOptions,
+) -> RessaReport {
+    let ctx = ExplorerContext {
+        record_provenance: options.provenance,
+        trace: options
+            .trace
+            .then(|| Rc::new(RefCell::new(RessaTrace::default()))),
+        ..Default::default()
+    };
+    let ExplorerContext { parser, trace, .. } = explore_ressas(ast, index, ressas, ctx);
+
+    let (result, provenance) = parser.into_result_with_provenance();
+    RessaReport {
+        result,
+        provenance: options.provenance.then_some(provenance),
+        trace: trace.map(|trace| trace.take()),
+    }
+}
+
+fn explore_ressas(
+    ast: &[ModuleComponent],
+    project_index: &LaastIndex,
+    ressas: Vec<NodePattern>,
+    mut ctx: ExplorerContext,
+) -> ExplorerContext {
+    // Specialize each root pattern to each of its languages, adding all inferred languages
+    // (ReSSA languages which are not specified and assumed to be the same as the parent's)
+    let (roots, ressas): (Vec<_>, Vec<_>) = ressas
         .into_iter()
-        .map(|ressa| {
-            let lang = ressa.get_language();
-            ressa.cascade_language(lang)
+        .enumerate()
+        .flat_map(|(root_pattern, ressa)| {
+            ressa
+                .root_languages()
+                .into_iter()
+                .map(move |lang| (root_pattern, ressa.clone().cascade_language(lang)))
         })
-        .collect::<Vec<_>>();
-
-    // Index the AST
-    let project_index = compute_index_languages(
-        &ressas,
-        ast.iter().map(|module| module as &dyn Indexable).collect(),
-    );
+        .unzip();
 
     // Explore
-    let mut ctx = ExplorerContext::default();
-    for ressa in ressas.into_iter() {
+    let mut current_root = None;
+    for (root_pattern, ressa) in roots.into_iter().zip(ressas) {
+        if current_root != Some(root_pattern) {
+            ctx.begin_root_pattern(root_pattern);
+            current_root = Some(root_pattern);
+        }
         match ressa.language {
             // Wildcard language (apply to any language)
             Some(Language::Unknown) => {
                 for module in ast.iter() {
-                    module.explore(&ressa, &mut ctx, &project_index);
+                    module.explore(&ressa, &mut ctx, project_index);
                 }
             }
 
             Some(_) => {
                 // Apply to targeted language
-                if let Some(roots) = project_index.get_roots(ressa.get_language()) {
-                    for module in roots.iter() {
-                        module.explore(&ressa, &mut ctx, &project_index);
-                    }
+                for module in project_index.get_roots(&[ressa.get_language()]) {
+                    module.explore(&ressa, &mut ctx, project_index);
                 }
             }
 
@@ -72,7 +251,5 @@ pub fn run_ressa_parse(ast: &mut Vec<ModuleComponent>, ressas: Vec<NodePattern>)
             None => panic!("Root parser with no language after language resolution step"),
         }
     }
-
-    // Clean and return context
-    ctx.parser.into()
+    ctx
 }
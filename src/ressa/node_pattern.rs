@@ -1,13 +1,18 @@
-use super::{ExplorerContext, LaastIndex, RessaNodeExplorer};
+use super::{
+    Constraint, ExplorerContext, Indexable, IntoNodeView, LaastIndex, Provenance,
+    RessaNodeExplorer, TraceEvent, TracedNode, TracedPattern,
+};
 // use super::ressaDispatch;
 use super::{pattern_parser::NodePatternParser, Executor};
 use crate::ast::*;
 use crate::prophet::*;
 use crate::ressa::explorer::choose_exit;
 use derive_new::new;
+use itertools::Itertools;
 use regex::Regex;
-use serde::Deserialize;
-use std::cell::RefCell;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 
 use super::{ContextLocalVariableActions, ContextObjectActions, ParserContext};
 
@@ -33,6 +38,11 @@ pub struct NodePattern {
     #[serde(default)]
     pub compiled_auxiliary_pattern: RefCell<Option<CompiledPattern>>,
 
+    /// Whether the patterns failed to compile, which is only reported once
+    #[serde(skip)]
+    #[new(default)]
+    pub compile_failed: Cell<bool>,
+
     /// Sub-patterns for this node pattern to be matched in the AST.
     /// Some subpatterns may be specified as required.
     pub subpatterns: Vec<NodePattern>,
@@ -54,9 +64,54 @@ pub struct NodePattern {
     #[serde(default = "bool::default")]
     pub transparent: bool,
 
-    /// Language this node applies to
-    #[serde(default = "Option::default")]
+    /// Language this node applies to, resolved from `languages` and the parent's language
+    #[serde(skip)]
     pub language: Option<Language>,
+
+    /// Languages this node applies to, as written by the user (a language, or a list of them).
+    /// A root pattern is applied to each language in turn; other patterns take their parent's
+    /// language if it is in the list, and the first language listed otherwise.
+    #[serde(rename = "language", default, deserialize_with = "one_or_many")]
+    #[new(default)]
+    pub languages: Vec<Language>,
+
+    /// Replacement regexes for nodes of specific languages
+    #[serde(default)]
+    #[new(default)]
+    pub overrides: HashMap<Language, PatternOverride>,
+
+    /// Constraints on the surroundings of a matched node (ancestors and siblings)
+    #[serde(default)]
+    #[new(default)]
+    pub constraints: Vec<Constraint>,
+
+    /// Invert the pattern, so the parent only matches if the match count falls outside
+    /// `min_count..=max_count` (by default, if this pattern is not found at all)
+    #[serde(default)]
+    #[new(default)]
+    pub negated: bool,
+
+    /// Minimum number of children this pattern must be found in for the parent to match
+    #[serde(default)]
+    #[new(default)]
+    pub min_count: Option<usize>,
+
+    /// Maximum number of children this pattern may be found in for the parent to match
+    #[serde(default)]
+    #[new(default)]
+    pub max_count: Option<usize>,
+
+    /// How MethodParam subpatterns are matched against a method's parameters, and argument
+    /// subpatterns against a call's arguments or a declaration's variables
+    #[serde(default)]
+    #[new(default)]
+    pub argument_matching: ArgumentMatching,
+
+    /// Name of the parameter or keyword argument this pattern is matched against, when its
+    /// parent uses `keyword` argument matching
+    #[serde(default)]
+    #[new(default)]
+    pub keyword: Option<String>,
 }
 
 impl NodePattern {
@@ -74,58 +129,154 @@ impl NodePattern {
         self.identifier == node.into_ressa_node() || self.transparent
     }
 
+    /// Determine whether the parent's match is decided by counting this pattern's matches,
+    /// rather than by `essential`
+    pub fn is_counted(&self) -> bool {
+        self.negated || self.min_count.is_some() || self.max_count.is_some()
+    }
+
+    /// Determine whether the number of children this pattern was found in satisfies it
+    pub fn count_satisfied(&self, count: usize) -> bool {
+        let min_count = self.min_count.unwrap_or(if self.negated { 1 } else { 0 });
+        let max_count = self.max_count.unwrap_or(usize::MAX);
+        let in_range = min_count <= count && count <= max_count;
+        in_range != self.negated
+    }
+
+    /// Languages a root pattern is applied to, in order and each once. Listing any language
+    /// (`N/A`) applies the pattern to every language a single time.
+    pub fn root_languages(&self) -> Vec<Language> {
+        match self.language {
+            Some(lang) => vec![lang],
+            None if self.languages.is_empty() => vec![Language::default()],
+            None if self.languages.contains(&Language::Unknown) => vec![Language::Unknown],
+            None => self.languages.iter().copied().unique().collect(),
+        }
+    }
+
     // Consume self to update language references. Unsure if this is the best way.
     pub fn cascade_language(mut self, parent_lang: Language) -> NodePattern {
+        // Counted patterns must only report children they were actually found in
+        if self.is_counted() {
+            self.essential = true;
+        }
+
         // Update own language if unknown
         let parent_lang = match self.language {
             Some(lang) => lang,
             None => {
-                self.language = Some(parent_lang);
-                parent_lang
+                let lang = if self.languages.is_empty() || self.languages.contains(&parent_lang) {
+                    parent_lang
+                } else {
+                    self.languages[0]
+                };
+                self.language = Some(lang);
+                lang
             }
         };
 
+        // Use the regexes for that language
+        if let Some(PatternOverride {
+            pattern,
+            auxiliary_pattern,
+        }) = self.overrides.remove(&parent_lang)
+        {
+            if let Some(pattern) = pattern {
+                self.pattern = pattern;
+            }
+            if auxiliary_pattern.is_some() {
+                self.auxiliary_pattern = auxiliary_pattern;
+            }
+        }
+
         // Cascade to children
         self.subpatterns = self
             .subpatterns
             .into_iter()
             .map(|child| child.cascade_language(parent_lang))
             .collect();
+        self.constraints = self
+            .constraints
+            .into_iter()
+            .map(|constraint| constraint.cascade_language(parent_lang))
+            .collect();
         self
     }
 
-    /// Lazy-compile the regexes on this NodePattern
-    pub fn lazy_compile(&self) -> Option<()> {
+    /// Lazy-compile the regexes on this NodePattern. A failure to compile is logged and traced
+    /// the first time, and fails every later match without compiling again.
+    pub fn lazy_compile(&self, ctx: &ExplorerContext) -> Option<()> {
+        if self.compile_failed.get() {
+            return None;
+        }
+        match self.try_compile() {
+            Ok(()) => Some(()),
+            Err(error) => {
+                tracing::warn!("Error compiling pattern: {:#?}", error);
+                ctx.trace(|| TraceEvent::InvalidPattern {
+                    pattern: TracedPattern::new(self, ctx.frame_number + 1),
+                    error: error.to_string(),
+                });
+                self.compile_failed.set(true);
+                None
+            }
+        }
+    }
+
+    fn try_compile(&self) -> Result<(), regex::Error> {
         let mut compiled_pattern = self.compiled_pattern.borrow_mut();
         if compiled_pattern.is_none() {
-            *compiled_pattern = Some(compile_compiled_pattern(&*self.pattern)?);
+            *compiled_pattern = Some(CompiledPattern::from_pattern(&*self.pattern)?);
         }
         let mut compiled_auxiliary_pattern = self.compiled_auxiliary_pattern.borrow_mut();
         if compiled_auxiliary_pattern.is_none() {
             if let Some(auxiliary_pattern) = &self.auxiliary_pattern {
-                *compiled_auxiliary_pattern = Some(compile_compiled_pattern(&*auxiliary_pattern)?);
+                *compiled_auxiliary_pattern =
+                    Some(CompiledPattern::from_pattern(auxiliary_pattern)?);
             }
         }
-        Some(())
+        Ok(())
     }
 }
 
+/// Regexes replacing a pattern's own for nodes of one language
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PatternOverride {
+    pub pattern: Option<String>,
+    pub auxiliary_pattern: Option<String>,
+}
+
+/// Deserialize a single language, or a list of them
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Language>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Language),
+        Many(Vec<Language>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(lang) => vec![lang],
+        OneOrMany::Many(langs) => langs,
+    })
+}
+
 impl NodeLanguage for NodePattern {
     fn get_language(&self) -> Language {
         self.language.unwrap_or_default()
     }
 }
 
-/// Optionally compile a provided pattern, if possible
-pub fn compile_compiled_pattern(pattern: &str) -> Option<CompiledPattern> {
-    let compiled_result = super::CompiledPattern::from_pattern(pattern);
-    match compiled_result {
-        Ok(compiled_result) => Some(compiled_result),
-        Err(error) => {
-            tracing::warn!("Error compiling pattern: {:#?}", error);
-            None
-        }
-    }
+/// Verify all constraints on the pattern hold for the node
+fn check_constraints(
+    pattern: &NodePattern,
+    node: &dyn Indexable,
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool {
+    pattern
+        .constraints
+        .iter()
+        .all(|constraint| constraint.check(node, ctx, index))
 }
 
 /// Parse the results
@@ -149,47 +300,71 @@ fn parse<N: NodePatternParser + RessaNodeExplorer>(
 }
 
 /// Run general ReSSA match acquired routine: compile patterns if needed, maintain context transactions, parse the node pattern, and
-pub fn ressa_node_parse<N: NodePatternParser + RessaNodeExplorer>(
+pub fn ressa_node_parse<N: NodePatternParser + Indexable + IntoNodeView>(
     pattern: &NodePattern,
     node: &N,
     ctx: &mut ExplorerContext,
     index: &LaastIndex,
 ) -> Option<()> {
     // Lazily compile patterns
-    pattern.lazy_compile()?;
+    pattern.lazy_compile(ctx)?;
 
     // Search
     ctx.frame_number += 1;
-
-    let mut transaction = ctx.clone();
-    let passed = if parse(pattern, node, &mut transaction, index) {
-        if pattern.callback.is_some() {
-            // let tmp = transaction.clone();
-            match Executor::get().execute(pattern, transaction.parser) {
-                Ok(new_ctx) => {
-                    ctx.parser = new_ctx;
-                    true
-                }
-                Err(err) => {
-                    tracing::warn!(
-                        "Failed to execute callback ({}) for: {:?}",
-                        err,
-                        pattern.callback
-                    );
-                    false
-                }
+    let depth = ctx.frame_number;
+    let traced = || TracedPattern::new(pattern, depth);
+    ctx.trace(|| TraceEvent::Considered {
+        pattern: traced(),
+        node: TracedNode::from(&ctx.describe(node)),
+    });
+
+    let checkpoint = ctx.checkpoint();
+    let passed = if !parse(pattern, node, ctx, index) {
+        false
+    } else if !check_constraints(pattern, node, ctx, index) {
+        ctx.trace(|| TraceEvent::ConstraintFailed { pattern: traced() });
+        false
+    } else if pattern.callback.is_some() && ctx.constraint_stack == 0 {
+        // Callbacks of patterns matched while checking constraints are not run
+        let view = ctx.describe(node);
+        if ctx.record_provenance {
+            let origin = Provenance::new(ctx.root_pattern, &view);
+            ctx.parser.set_origin(Some(origin));
+        }
+        match Executor::get().execute(pattern, &mut ctx.parser, view) {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to execute callback ({}) for: {:?}",
+                    err,
+                    pattern.callback
+                );
+                ctx.trace(|| TraceEvent::CallbackFailed {
+                    pattern: traced(),
+                    error: err.to_string(),
+                });
+                false
             }
-        } else {
-            *ctx = transaction;
-            true
         }
     } else {
-        false
+        true
     };
+    if !passed {
+        ctx.rollback(checkpoint);
+    }
+    ctx.trace(|| {
+        let pattern = traced();
+        if passed {
+            TraceEvent::Matched { pattern }
+        } else {
+            TraceEvent::Rejected { pattern }
+        }
+    });
 
     ctx.frame_number -= 1;
     if ctx.frame_number == 0 {
         ctx.parser.clear_variables();
+        ctx.parser.commit();
     }
 
     // Resume the search where we left off, or pass on an error indicating a required subpattern
@@ -201,7 +376,27 @@ pub fn ressa_node_parse<N: NodePatternParser + RessaNodeExplorer>(
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+/// Strategies for pairing argument subpatterns with the arguments (or parameters) of a node.
+/// A non-essential subpattern accepts any argument, capturing variables if it matches.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentMatching {
+    /// The i-th subpattern matches the i-th argument, and there are exactly as many arguments as subpatterns
+    Exact,
+
+    /// The subpatterns match arguments in order, though other arguments may come between them
+    #[default]
+    Subsequence,
+
+    /// Each subpattern matches a different argument, in any order
+    Unordered,
+
+    /// Subpatterns with a `keyword` match the argument passed (or parameter declared) under that
+    /// name; the rest match the remaining arguments as a subsequence
+    Keyword,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum NodeType {
     // Prophet nodes
     ClassOrInterface,
@@ -401,6 +596,8 @@ impl CompiledPattern {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ressa::run_ressa_parse;
+    use crate::test_util::parse_files;
 
     #[test]
     fn test_bson() {
@@ -423,4 +620,51 @@ mod tests {
                 .as_str()
         );
     }
+
+    #[test]
+    fn root_languages_are_listed_once() {
+        let languages = |languages: &str| {
+            let pattern = format!(
+                r#"{{"identifier": "CallExpr", "pattern": "", "essential": true, "language": {}, "subpatterns": []}}"#,
+                languages
+            );
+            serde_json::from_str::<NodePattern>(&pattern)
+                .unwrap()
+                .root_languages()
+        };
+        assert_eq!(
+            vec![Language::Java, Language::Cpp],
+            languages(r#"["Java", "Cpp", "Java"]"#)
+        );
+        assert_eq!(vec![Language::Unknown], languages(r#"["Java", "N/A"]"#));
+    }
+
+    #[test]
+    fn multi_language_pattern() {
+        let java = r#"
+        class OrderService {
+            public void place(Order order) {
+                repository.save(order);
+            }
+        }
+        "#;
+        let cpp = r#"
+        void place(Order order) {
+            repository.persist(order);
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "CallExpr",
+            "pattern": "#{op}(save)",
+            "essential": true,
+            "language": ["Java", "Cpp"],
+            "overrides": {"Cpp": {"pattern": "#{op}(persist)"}},
+            "subpatterns": [],
+            "callback": "ctx.save(ctx.get_variable(\"op\").unwrap(), #{});"
+        }]"##;
+        let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+        let mut modules = parse_files(&[("OrderService.java", java), ("orders.cpp", cpp)]);
+        let result = run_ressa_parse(&mut modules, patterns);
+        assert_eq!(vec!["persist", "save"], result.keys().collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,321 @@
+use rune::Any;
+
+use super::IntoRessaNode;
+use crate::ast::*;
+use crate::prophet::*;
+
+/// Read-only description of a matched LAAST node, handed to callbacks as `node`
+#[derive(Default, Debug, Clone, Any)]
+pub struct NodeView {
+    kind: String,
+    name: String,
+    auxiliary: Option<String>,
+    language: String,
+    file: Option<String>,
+    line_begin: Option<i64>,
+    line_end: Option<i64>,
+    children: Vec<NodeView>,
+    parents: Vec<NodeView>,
+}
+
+impl NodeView {
+    fn new(kind: &str, name: &str, auxiliary: Option<&str>, language: Language) -> NodeView {
+        NodeView {
+            kind: kind.into(),
+            name: name.into(),
+            auxiliary: auxiliary.map(String::from),
+            language: format!("{:?}", language),
+            ..Default::default()
+        }
+    }
+
+    fn with_file(mut self, file: &str) -> NodeView {
+        self.file = Some(file.into());
+        self
+    }
+
+    fn with_lines(mut self, line_begin: i32, line_end: i32) -> NodeView {
+        self.line_begin = Some(line_begin as i64);
+        self.line_end = Some(line_end as i64);
+        self
+    }
+
+    fn with_children<'a, T: IntoNodeView + 'a>(
+        mut self,
+        children: impl IntoIterator<Item = &'a T>,
+    ) -> NodeView {
+        self.children.extend(children.into_iter().map(|child| {
+            // Children are summaries, so drop their own children
+            let mut view = child.into_node_view();
+            view.children.clear();
+            view
+        }));
+        self
+    }
+
+    /// Attach the chain of enclosing modules, classes and methods (outermost first)
+    pub fn with_parents(mut self, parents: Vec<NodeView>) -> NodeView {
+        self.parents = parents;
+        self
+    }
+
+    /// The node type, as named in `NodeType` for matchable nodes
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    /// The string the node's `pattern` is matched against
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The string the node's `auxiliary_pattern` is matched against, if any
+    pub fn auxiliary(&self) -> Option<String> {
+        self.auxiliary.clone()
+    }
+
+    pub fn language(&self) -> String {
+        self.language.clone()
+    }
+
+    /// The file the node was declared in, where the AST records one (modules, classes and methods)
+    pub fn file(&self) -> Option<String> {
+        self.file.clone()
+    }
+
+    pub fn line_begin(&self) -> Option<i64> {
+        self.line_begin
+    }
+
+    pub fn line_end(&self) -> Option<i64> {
+        self.line_end
+    }
+
+    /// Shallow descriptions of the node's direct children (arguments, parameters, annotations, ...)
+    pub fn children(&self) -> Vec<NodeView> {
+        self.children.clone()
+    }
+
+    pub fn parents(&self) -> Vec<NodeView> {
+        self.parents.clone()
+    }
+}
+
+/// Describe a node for consumption by a callback
+pub trait IntoNodeView {
+    // Same reasoning as IntoRessaNode for borrowing self
+    #[allow(clippy::wrong_self_convention)]
+    fn into_node_view(&self) -> NodeView;
+}
+
+/// Name of the node type as it appears in ReSSA patterns
+fn kind_of(node: &impl IntoRessaNode) -> String {
+    format!("{:?}", node.into_ressa_node())
+}
+
+/// Name of an expression's variant
+fn expr_kind(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::AssignExpr(_) => "AssignExpr",
+        Expr::BinaryExpr(_) => "BinaryExpr",
+        Expr::UnaryExpr(_) => "UnaryExpr",
+        Expr::CallExpr(_) => "CallExpr",
+        Expr::EndpointCallExpr(_) => "EndpointCallExpr",
+        Expr::IndexExpr(_) => "IndexExpr",
+        Expr::ParenExpr(_) => "ParenExpr",
+        Expr::DotExpr(_) => "DotExpr",
+        Expr::IncDecExpr(_) => "IncDecExpr",
+        Expr::InitListExpr(_) => "InitListExpr",
+        Expr::LogExpr(_) => "LogExpr",
+        Expr::LambdaExpr(_) => "LambdaExpr",
+        Expr::Ident(_) => "Ident",
+        Expr::Literal(_) => "Literal",
+        Expr::SwitchExpr(_) => "SwitchExpr",
+        Expr::CaseExpr(_) => "CaseExpr",
+        Expr::InstanceOfExpr(_) => "InstanceOfExpr",
+        Expr::MethodRefExpr(_) => "MethodRefExpr",
+    }
+}
+
+/// Best-effort name of an expression, joining member accesses with a '.'
+fn expr_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Ident(Ident { name, .. }) => name.clone(),
+        Expr::Literal(Literal { value, .. }) => value.clone(),
+        Expr::DotExpr(DotExpr { expr, selected, .. }) => {
+            format!("{}.{}", expr_name(expr), expr_name(selected))
+        }
+        Expr::CallExpr(CallExpr { name, .. }) => expr_name(name),
+        _ => String::new(),
+    }
+}
+
+impl IntoNodeView for ModuleComponent {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new("Module", &self.module_name, None, self.get_language())
+            .with_file(&self.component.component.path)
+    }
+}
+
+impl IntoNodeView for ClassOrInterfaceComponent {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.component.container_name,
+            Some(&self.component.component.package_name),
+            self.get_language(),
+        )
+        .with_file(&self.component.component.path)
+        .with_children(&self.annotations)
+        .with_children(&self.field_components)
+        .with_children(&self.constructors)
+        .with_children(&self.component.methods)
+    }
+}
+
+impl IntoNodeView for MethodComponent {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.component.instance_name,
+            Some(&self.return_type),
+            self.get_language(),
+        )
+        .with_file(&self.component.path)
+        .with_lines(self.line_begin, self.line_end)
+        .with_children(&self.annotations)
+        .with_children(&self.parameters)
+    }
+}
+
+impl IntoNodeView for MethodParamComponent {
+    fn into_node_view(&self) -> NodeView {
+        let view = NodeView::new(
+            &kind_of(self),
+            &self.parameter_name,
+            Some(&self.r#type),
+            self.get_language(),
+        );
+        match &self.annotation {
+            Some(annotations) => view.with_children(annotations),
+            None => view,
+        }
+    }
+}
+
+impl IntoNodeView for FieldComponent {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.field_name,
+            Some(&self.r#type),
+            self.get_language(),
+        )
+        .with_children(&self.annotations)
+    }
+}
+
+impl IntoNodeView for AnnotationComponent {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.name,
+            Some(&self.value),
+            self.get_language(),
+        )
+        .with_children(&self.key_value_pairs)
+    }
+}
+
+impl IntoNodeView for AnnotationValuePair {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.key,
+            Some(&self.value),
+            self.get_language(),
+        )
+    }
+}
+
+impl IntoNodeView for DeclStmt {
+    fn into_node_view(&self) -> NodeView {
+        let name = self
+            .variables
+            .iter()
+            .map(|var| var.ident.name.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        NodeView::new(&kind_of(self), &name, None, self.get_language())
+            .with_children(&self.variables)
+            .with_children(self.expressions.iter().flatten())
+    }
+}
+
+impl IntoNodeView for VarDecl {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.ident.name,
+            self.var_type.as_deref(),
+            self.get_language(),
+        )
+        .with_children(&self.annotation)
+    }
+}
+
+impl IntoNodeView for CallExpr {
+    fn into_node_view(&self) -> NodeView {
+        let (name, auxiliary) = match &*self.name {
+            Expr::DotExpr(DotExpr { expr, selected, .. }) => {
+                (expr_name(selected), Some(expr_name(expr)))
+            }
+            name => (expr_name(name), None),
+        };
+        NodeView::new(
+            &kind_of(self),
+            &name,
+            auxiliary.as_deref(),
+            self.get_language(),
+        )
+        .with_children(&self.args)
+    }
+}
+
+impl IntoNodeView for Ident {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(
+            &kind_of(self),
+            &self.name,
+            self.var_type.as_deref(),
+            self.get_language(),
+        )
+    }
+}
+
+impl IntoNodeView for Literal {
+    fn into_node_view(&self) -> NodeView {
+        NodeView::new(&kind_of(self), &self.value, None, self.get_language())
+    }
+}
+
+impl IntoNodeView for BinaryExpr {
+    fn into_node_view(&self) -> NodeView {
+        let op: String = self.op.clone().into();
+        NodeView::new(&kind_of(self), &op, None, self.get_language())
+            .with_children(vec![&*self.lhs, &*self.rhs])
+    }
+}
+
+impl IntoNodeView for Expr {
+    fn into_node_view(&self) -> NodeView {
+        match self {
+            Expr::CallExpr(expr) => expr.into_node_view(),
+            Expr::Ident(expr) => expr.into_node_view(),
+            Expr::Literal(expr) => expr.into_node_view(),
+            Expr::BinaryExpr(expr) => expr.into_node_view(),
+            // Unmatchable expressions are only described by their variant and best-effort name
+            expr => NodeView::new(expr_kind(expr), &expr_name(expr), None, expr.get_lang()),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+
+use rune::{runtime::ConstValue, FromValue};
+
+use super::{explore_ressas, index_modules, NodePattern, RessaResult};
+use crate::ModuleComponent;
+
+/// Run the user-defined parsers like `run_ressa_parse`, splitting the modules between up to
+/// `threads` threads (or one per available core, if zero).
+///
+/// Each thread applies every root pattern, in order, to its own contiguous run of modules with
+/// its own context, so patterns only see objects saved from the same thread; this suits patterns
+/// whose matches in one module do not depend on another. Afterwards, the objects from each
+/// thread are merged in module order: objects with the same name have their attributes merged,
+/// vectors are concatenated, and any other value is replaced by the later one. Objects are not
+/// shared between threads, so patterns relying on objects saved from other modules should be run
+/// sequentially. Only plain data (see `ConstValue`) can leave a thread: if a thread saves any
+/// other value, like a function, the patterns are run again sequentially over all modules.
+pub fn run_ressa_parse_parallel(
+    ast: &[ModuleComponent],
+    ressas: Vec<NodePattern>,
+    threads: usize,
+) -> RessaResult {
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        threads => threads,
+    };
+    if ast.is_empty() {
+        return RessaResult::default();
+    }
+    let chunk_size = ast.len().div_ceil(threads);
+
+    let partial_results = std::thread::scope(|scope| {
+        let handles = ast
+            .chunks(chunk_size)
+            .map(|modules| {
+                let ressas = ressas.clone();
+                scope.spawn(move || {
+                    let result: RessaResult = explore_ressas(
+                        modules,
+                        &index_modules(modules),
+                        ressas,
+                        Default::default(),
+                    )
+                    .parser
+                    .into();
+                    into_const(result)
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("ReSSA thread panicked"))
+            .collect::<Option<Vec<_>>>()
+    });
+    let partial_results = match partial_results {
+        Some(partial_results) => partial_results,
+        None => {
+            tracing::warn!("Running ReSSA sequentially, as values other than data were saved");
+            return explore_ressas(ast, &index_modules(ast), ressas, Default::default())
+                .parser
+                .into();
+        }
+    };
+
+    let mut merged = BTreeMap::new();
+    for result in partial_results {
+        for (name, value) in result {
+            match merged.get_mut(&name) {
+                Some(existing) => merge(existing, value),
+                None => {
+                    merged.insert(name, value);
+                }
+            }
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(name, value)| (name, value.into_value()))
+        .collect()
+}
+
+/// Convert a thread's objects into a form that can be sent between threads, unless one of them
+/// holds a value other than data
+fn into_const(result: RessaResult) -> Option<BTreeMap<String, ConstValue>> {
+    result
+        .into_iter()
+        .map(|(name, value)| match ConstValue::from_value(value) {
+            Ok(value) => Some((name, value)),
+            Err(err) => {
+                tracing::warn!("Object {} cannot leave a ReSSA thread: {:?}", name, err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Merge a later thread's value into an earlier thread's
+fn merge(into: &mut ConstValue, from: ConstValue) {
+    match (into, from) {
+        (ConstValue::Object(into), ConstValue::Object(from)) => {
+            for (key, value) in from {
+                match into.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        into.insert(key, value);
+                    }
+                }
+            }
+        }
+        (ConstValue::Vec(into), ConstValue::Vec(from)) => into.extend(from),
+        (into, from) => *into = from,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_ressa_parse_parallel;
+    use crate::ressa::{run_ressa_parse, NodePattern};
+    use crate::test_util::parse_files;
+
+    #[test]
+    fn matches_serial_run() {
+        let services = ["Order", "Payment", "Shipping", "Invoice", "Refund"]
+            .iter()
+            .map(|name| {
+                (
+                    format!("{}/{}Service.java", name.to_lowercase(), name),
+                    format!(
+                        r#"
+                        class {0}Service {{
+                            public void place({0} item) {{
+                                repository.save(item);
+                            }}
+                        }}
+                        "#,
+                        name
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+        let files = services
+            .iter()
+            .map(|(name, code)| (name.as_str(), code.as_str()))
+            .collect::<Vec<_>>();
+        let modules = parse_files(&files);
+        assert_eq!(5, modules.len());
+
+        let patterns = r##"[{
+            "identifier": "ClassOrInterface",
+            "pattern": "#{service}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "ctx.save(ctx.get_variable(\"service\").unwrap(), #{kind: \"service\"}); let all = ctx.get_or_save(\"services\", #{names: []}); all.names.push(ctx.get_variable(\"service\").unwrap()); ctx.save(\"services\", all);"
+        }, {
+            "identifier": "ClassOrInterface",
+            "pattern": "#{service}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [{
+                "identifier": "CallExpr",
+                "pattern": "#{op}(save)",
+                "essential": true,
+                "subpatterns": []
+            }],
+            "callback": "let service = ctx.get(ctx.get_variable(\"service\").unwrap()).unwrap(); service.persists = true; ctx.save(ctx.get_variable(\"service\").unwrap(), service);"
+        }]"##;
+        let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+
+        let serial = run_ressa_parse(&mut modules.clone(), patterns.clone());
+        for threads in [1, 2, 3] {
+            let parallel = run_ressa_parse_parallel(&modules, patterns.clone(), threads);
+            assert_eq!(
+                serde_json::to_value(&serial).unwrap(),
+                serde_json::to_value(&parallel).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn runs_sequentially_when_saving_functions() {
+        let files = [
+            ("order/OrderService.java", "class OrderService {}"),
+            ("payment/PaymentService.java", "class PaymentService {}"),
+        ];
+        let modules = parse_files(&files);
+        let patterns = r##"[{
+            "identifier": "ClassOrInterface",
+            "pattern": "#{service}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "let all = ctx.get_or_save(\"services\", #{names: [], format: |name| name}); all.names.push(ctx.get_variable(\"service\").unwrap()); ctx.save(\"services\", all);"
+        }]"##;
+        let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+
+        // Both services are seen by the same context, rather than the functions being dropped
+        let parallel = run_ressa_parse_parallel(&modules, patterns, 2);
+        let services = crate::ressa::extract_object(
+            parallel["services"]
+                .clone()
+                .into_object()
+                .unwrap()
+                .take()
+                .unwrap(),
+        );
+        let names = services["names"]
+            .clone()
+            .into_vec()
+            .unwrap()
+            .take()
+            .unwrap();
+        assert_eq!(2, names.len());
+        assert!(services.contains_key("format"));
+    }
+}
@@ -1,4 +1,7 @@
-use super::{CompiledPattern, ExplorerContext, NodePattern, ParserContext, RessaNodeExplorer};
+use super::{
+    AbortReason, ArgumentMatching, CompiledPattern, ExplorerContext, NodePattern,
+    RessaNodeExplorer, TraceEvent, TracedPattern,
+};
 use super::{LaastIndex, NodeType};
 use crate::ast::*;
 use crate::{prophet::*, ressa::choose_exit};
@@ -18,69 +21,46 @@ fn write_to_context(
     to_match: &str,
     essential: bool,
     pattern: &Option<CompiledPattern>,
-    ctx: &mut ParserContext,
-) -> Option<()> {
-    match pattern.as_ref() {
-        Some(compiled_pattern) if compiled_pattern.match_and_insert(to_match, ctx) => Some(()),
-        Some(_) if essential => None,
-        _ => Some(()),
-    }
-}
-
-fn match_subsequence<T: RessaNodeExplorer>(
-    params: &[&NodePattern],
-    explorable: &[T],
     ctx: &mut ExplorerContext,
-    index: &LaastIndex,
 ) -> Option<()> {
-    let (mut start, mut end) = (0_usize, params.len());
-    // let mut matched = true;
-
-    while start < explorable.len() {
-        // Pre
-        //        matched = true;
-        let mut pattern_iter = params.iter();
-
-        // Perform subsequence matching
-        for i in start..end {
-            let pattern = pattern_iter.next()?;
-            if explorable.get(i)?.explore(pattern, ctx, index).is_none() {
-                //                matched = false;
-                break;
-            }
-        }
-
-        // Post
-        // if !matched {
-        start += 1;
-        if end < explorable.len() {
-            end += 1;
+    match pattern.as_ref() {
+        Some(compiled) if compiled.match_and_insert(to_match, &mut ctx.parser) => Some(()),
+        Some(compiled) => {
+            trace_regex_failure(compiled, to_match, ctx);
+            choose_exit(essential, false)
         }
-        // } else {
-        //     break;
-        // }
+        None => Some(()),
     }
+}
 
-    // Determine if we matched the pattern at some point
-    // if matched {
-    //     Some(())
-    // } else {
-    //     None
-    // }
-    Some(())
+fn trace_regex_failure(compiled: &CompiledPattern, input: &str, ctx: &ExplorerContext) {
+    ctx.trace(|| TraceEvent::RegexFailed {
+        depth: ctx.frame_number,
+        regex: compiled.pattern.to_string(),
+        input: input.into(),
+    });
 }
 
 /// Verify if an Option<Regex> matches a specific string; if it fails, exits
 macro_rules! verify_match {
     ( $match_str:expr, $pattern:expr, $ctx:expr, $essential:expr ) => {
         if let Some(compiled) = $pattern {
-            if !compiled.matches($match_str, $ctx) {
+            if !compiled.matches($match_str, &$ctx.parser) {
+                trace_regex_failure(compiled, $match_str, $ctx);
                 quit!($essential);
             }
         }
     };
 }
 
+/// Note that a subpattern failed its parent
+fn trace_abort(subpattern: &NodePattern, reason: AbortReason, ctx: &ExplorerContext) {
+    ctx.trace(|| TraceEvent::SubpatternAborted {
+        pattern: TracedPattern::new(subpattern, ctx.frame_number),
+        reason,
+    });
+}
+
 /// Exit a search, voting to abort or continue
 macro_rules! quit {
     ( $essential:expr ) => {
@@ -111,24 +91,285 @@ macro_rules! explore_all {
     }};
 }
 
-/// Explores all subpatterns, verifying each essential pattern matches
+/// Count the elements in the provided collections the pattern is found in. Whatever negated
+/// patterns find is rolled back, and their callbacks are not run.
+#[macro_export]
+macro_rules! count_all {
+    ( $pattern:expr, $ctx:expr, $index:expr, $( $explorable:expr ),+ ) => {{
+        let checkpoint = $ctx.checkpoint();
+        if $pattern.negated {
+            $ctx.constraint_stack += 1;
+        }
+
+        let mut count = 0_usize;
+        $(
+            for x in $explorable.iter() {
+                if x.explore($pattern, $ctx, $index).is_some() {
+                    count += 1;
+                }
+            }
+        )*
+
+        if $pattern.negated {
+            $ctx.constraint_stack -= 1;
+            $ctx.rollback(checkpoint);
+        }
+        count
+    }};
+}
+
+/// Explores all subpatterns, verifying each essential pattern matches and each counted pattern
+/// is found the required number of times
 #[macro_export]
 macro_rules! explore_all_subpatterns {
     ( $subpatterns:expr, $ctx:expr, $index:expr, $( $explorable:expr ),+ ) => {
         use crate::ressa::explorer::choose_exit;
 
         for subpattern in $subpatterns.iter() {
+            if subpattern.is_counted() {
+                if !subpattern.count_satisfied(count_all!(subpattern, $ctx, $index, $( $explorable ),+)) {
+                    trace_abort(subpattern, AbortReason::CountUnsatisfied, $ctx);
+                    return None;
+                }
+                continue;
+            }
+
             let mut explore_all_found_essential = false;
             $(
                 if explore_all!(subpattern, $ctx, $index, $explorable).is_some() {
                     explore_all_found_essential = true;
                 }
             )*
-            choose_exit(subpattern.essential, explore_all_found_essential)?;
+            if choose_exit(subpattern.essential, explore_all_found_essential).is_none() {
+                trace_abort(subpattern, AbortReason::NotFound, $ctx);
+                return None;
+            }
         }
     };
 }
 
+/// Exposes the name an argument is passed under (or a parameter is declared under), for keyword matching
+trait KeywordArgument: RessaNodeExplorer {
+    /// The argument's keyword, and the node holding its value
+    fn keyword(&self) -> Option<(&str, &dyn RessaNodeExplorer)>;
+}
+
+impl KeywordArgument for Expr {
+    fn keyword(&self) -> Option<(&str, &dyn RessaNodeExplorer)> {
+        // Keyword arguments look like an assignment of the value to the keyword, which some
+        // languages lower to a binary expression
+        match self {
+            Expr::AssignExpr(AssignExpr { lhs, rhs, .. }) => match (&lhs[..], &rhs[..]) {
+                ([Expr::Ident(Ident { name, .. })], [value]) => Some((name, value)),
+                _ => None,
+            },
+            Expr::BinaryExpr(BinaryExpr { lhs, op, rhs, .. }) if *op == Op::Equal => {
+                match lhs.as_ref() {
+                    Expr::Ident(Ident { name, .. }) => Some((name, rhs.as_ref())),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl KeywordArgument for MethodParamComponent {
+    fn keyword(&self) -> Option<(&str, &dyn RessaNodeExplorer)> {
+        Some((&self.parameter_name, self))
+    }
+}
+
+impl KeywordArgument for VarDecl {
+    fn keyword(&self) -> Option<(&str, &dyn RessaNodeExplorer)> {
+        Some((&self.ident.name, self))
+    }
+}
+
+/// Match argument subpatterns against the provided arguments, using the requested strategy
+fn match_arguments<T: KeywordArgument>(
+    params: &[&NodePattern],
+    explorable: &[T],
+    mode: ArgumentMatching,
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> Option<()> {
+    // Counted patterns describe how many elements match, regardless of position
+    let (counted, params): (Vec<&NodePattern>, Vec<&NodePattern>) =
+        params.iter().partition(|param| param.is_counted());
+    for param in counted {
+        if !param.count_satisfied(count_all!(param, ctx, index, explorable)) {
+            trace_abort(param, AbortReason::CountUnsatisfied, ctx);
+            return None;
+        }
+    }
+
+    let args = explorable
+        .iter()
+        .map(|arg| arg as &dyn RessaNodeExplorer)
+        .collect_vec();
+    let matched = match mode {
+        ArgumentMatching::Exact => match_exact(&params, &args, ctx, index),
+        ArgumentMatching::Subsequence => match_ordered(&params, &args, ctx, index),
+        ArgumentMatching::Unordered => match_unordered(&params, &args, ctx, index),
+        ArgumentMatching::Keyword => match_keywords(&params, explorable, ctx, index),
+    };
+    if matched {
+        Some(())
+    } else {
+        ctx.trace(|| TraceEvent::ArgumentsUnmatched {
+            depth: ctx.frame_number,
+            mode,
+            patterns: params.len(),
+            arguments: explorable.len(),
+        });
+        None
+    }
+}
+
+/// Pair the patterns with the arguments by position
+fn match_exact(
+    params: &[&NodePattern],
+    args: &[&dyn RessaNodeExplorer],
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool {
+    params.len() == args.len()
+        && params
+            .iter()
+            .zip(args)
+            .all(|(param, arg)| arg.explore(param, ctx, index).is_some())
+}
+
+/// Match the patterns in order, each against the first remaining argument it is found in
+fn match_ordered(
+    params: &[&NodePattern],
+    args: &[&dyn RessaNodeExplorer],
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool {
+    // Taking the earliest match for each pattern leaves the most arguments for the rest
+    let mut args = args.iter().peekable();
+    params.iter().all(|param| {
+        if args.peek().is_none() {
+            // Non-essential patterns accept any argument, including a missing one
+            return !param.essential;
+        }
+        args.any(|arg| arg.explore(param, ctx, index).is_some())
+    })
+}
+
+/// Match each pattern against a different argument, in any order
+fn match_unordered(
+    params: &[&NodePattern],
+    args: &[&dyn RessaNodeExplorer],
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool {
+    if params.len() > args.len() {
+        return false;
+    }
+
+    // Find which arguments each pattern could match, without keeping anything it captures
+    let checkpoint = ctx.checkpoint();
+    let candidates = params
+        .iter()
+        .map(|param| {
+            (0..args.len())
+                .filter(|&arg| {
+                    let found = args[arg].explore(param, ctx, index).is_some();
+                    ctx.rollback(checkpoint);
+                    found
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    // Pair patterns with arguments, rearranging earlier pairs when an argument is contested
+    let mut paired: Vec<Option<usize>> = vec![None; args.len()];
+    for param in 0..params.len() {
+        if !pair_argument(
+            param,
+            &candidates,
+            &mut vec![false; args.len()],
+            &mut paired,
+        ) {
+            return false;
+        }
+    }
+
+    // Match the final pairs for real, in pattern order, so their variables are captured
+    paired
+        .iter()
+        .enumerate()
+        .filter_map(|(arg, param)| param.map(|param| (param, arg)))
+        .sorted()
+        .all(|(param, arg)| args[arg].explore(params[param], ctx, index).is_some())
+}
+
+/// Find an argument for the pattern via an augmenting path through the existing pairs
+fn pair_argument(
+    param: usize,
+    candidates: &[Vec<usize>],
+    visited: &mut Vec<bool>,
+    paired: &mut Vec<Option<usize>>,
+) -> bool {
+    for &arg in candidates[param].iter() {
+        if visited[arg] {
+            continue;
+        }
+        visited[arg] = true;
+        let free = match paired[arg] {
+            Some(other) => pair_argument(other, candidates, visited, paired),
+            None => true,
+        };
+        if free {
+            paired[arg] = Some(param);
+            return true;
+        }
+    }
+    false
+}
+
+/// Match patterns with a keyword against the argument with that keyword, then the remaining
+/// patterns against the remaining arguments as a subsequence
+fn match_keywords<T: KeywordArgument>(
+    params: &[&NodePattern],
+    explorable: &[T],
+    ctx: &mut ExplorerContext,
+    index: &LaastIndex,
+) -> bool {
+    let (named, positional): (Vec<&NodePattern>, Vec<&NodePattern>) =
+        params.iter().partition(|param| param.keyword.is_some());
+
+    let mut claimed = vec![false; explorable.len()];
+    for param in named {
+        let found = explorable.iter().enumerate().find_map(|(i, arg)| {
+            arg.keyword()
+                .filter(|(keyword, _)| Some(*keyword) == param.keyword.as_deref())
+                .map(|(_, value)| (i, value))
+        });
+        match found {
+            Some((i, value)) => {
+                claimed[i] = true;
+                if value.explore(param, ctx, index).is_none() {
+                    return false;
+                }
+            }
+            None if param.essential => return false,
+            None => {}
+        }
+    }
+
+    let remaining = explorable
+        .iter()
+        .zip(claimed)
+        .filter(|(_, claimed)| !claimed)
+        .map(|(arg, _)| arg as &dyn RessaNodeExplorer)
+        .collect_vec();
+    match_ordered(&positional, &remaining, ctx, index)
+}
+
 impl NodePatternParser for ClassOrInterfaceComponent {
     fn parse(
         &self,
@@ -141,13 +382,13 @@ impl NodePatternParser for ClassOrInterfaceComponent {
             &self.component.container_name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         write_to_context(
             &self.component.component.package_name,
             pattern.essential,
             &pattern.compiled_auxiliary_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
 
         // Check subpatterns
@@ -175,14 +416,14 @@ impl NodePatternParser for MethodComponent {
             &self.component.instance_name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
 
         write_to_context(
             &self.return_type,
             pattern.essential,
             &pattern.compiled_auxiliary_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
 
         // Match method parameters
@@ -191,7 +432,13 @@ impl NodePatternParser for MethodComponent {
             .iter()
             .filter(|child| matches!(child.identifier, crate::ressa::NodeType::MethodParam))
             .collect::<Vec<&NodePattern>>();
-        match_subsequence(&params, &self.parameters, ctx, index)?;
+        match_arguments(
+            &params,
+            &self.parameters,
+            pattern.argument_matching,
+            ctx,
+            index,
+        )?;
 
         // If there's a method body, explore it
         let mut tmp = vec![];
@@ -202,20 +449,18 @@ impl NodePatternParser for MethodComponent {
         };
 
         // Search unordered parts of the signature
-        for pattern in pattern
-            .subpatterns
-            .iter()
-            .filter(|child| !matches!(child.identifier, crate::ressa::NodeType::MethodParam))
-        {
-            explore_all!(
-                pattern,
-                ctx,
-                index,
-                self.annotations,
-                self.sub_methods,
-                body_nodes
-            )?;
-        }
+        explore_all_subpatterns!(
+            pattern
+                .subpatterns
+                .iter()
+                .filter(|child| !matches!(child.identifier, crate::ressa::NodeType::MethodParam))
+                .collect::<Vec<_>>(),
+            ctx,
+            index,
+            self.annotations,
+            self.sub_methods,
+            body_nodes
+        );
         Some(())
     }
 }
@@ -233,20 +478,20 @@ impl NodePatternParser for MethodParamComponent {
         verify_match!(
             &*self.parameter_name,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         write_to_context(
             &self.r#type,
             pattern.essential,
             &pattern.compiled_auxiliary_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         write_to_context(
             &self.parameter_name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
 
         // If there are annotation subnodes, check if they match the subpatterns--then exit
@@ -271,20 +516,20 @@ impl NodePatternParser for FieldComponent {
         verify_match!(
             &*self.field_name,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         write_to_context(
             &self.r#type,
             pattern.essential,
             &pattern.compiled_auxiliary_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         write_to_context(
             &self.field_name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         let mut expr_vec = Vec::new();
         if let Some(expr) = &self.expression {
@@ -361,7 +606,7 @@ impl NodePatternParser for DeclStmt {
         } else {
             // Case 3: multiple non-Decls to fewer Decls
             let real_expressions = self.expressions.iter().flatten().collect::<Vec<&Expr>>();
-            match_subsequence(
+            match_arguments(
                 // Get the actual pattern references from the subpatterns array using the indices in decl_patterns (I'm sorry.)
                 &pattern
                     .subpatterns
@@ -371,6 +616,7 @@ impl NodePatternParser for DeclStmt {
                     .map(|(_, p)| p)
                     .collect_vec(),
                 &self.variables,
+                pattern.argument_matching,
                 ctx,
                 index,
             )?;
@@ -394,7 +640,7 @@ impl NodePatternParser for VarDecl {
         verify_match!(
             &*self.ident.name,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         if pattern.auxiliary_pattern.is_some() {
@@ -402,7 +648,7 @@ impl NodePatternParser for VarDecl {
                 verify_match!(
                     &*var_type,
                     &*pattern.compiled_auxiliary_pattern.borrow(),
-                    &ctx.parser,
+                    ctx,
                     pattern.essential
                 );
             } else {
@@ -418,14 +664,14 @@ impl NodePatternParser for VarDecl {
             &self.ident.name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         if let Some(var_type) = &self.var_type {
             write_to_context(
                 var_type,
                 pattern.essential,
                 &pattern.compiled_auxiliary_pattern.borrow(),
-                &mut ctx.parser,
+                ctx,
             )
         } else {
             Some(())
@@ -488,14 +734,14 @@ impl NodePatternParser for CallExpr {
             raw_name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         if let Some(lhs) = auxiliary_name {
             write_to_context(
                 lhs,
                 pattern.essential,
                 &pattern.compiled_auxiliary_pattern.borrow(),
-                &mut ctx.parser,
+                ctx,
             )?;
         } else if pattern.auxiliary_pattern.is_some() {
             quit!(pattern.essential);
@@ -519,7 +765,7 @@ impl NodePatternParser for CallExpr {
             //     _ => false,
             // })
             .collect::<Vec<&NodePattern>>();
-        match_subsequence(&params, &self.args, ctx, index)
+        match_arguments(&params, &self.args, pattern.argument_matching, ctx, index)
     }
 }
 
@@ -534,13 +780,13 @@ impl NodePatternParser for AnnotationComponent {
         verify_match!(
             &*self.name,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         verify_match!(
             &*self.value,
             &*pattern.compiled_auxiliary_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
 
@@ -552,13 +798,13 @@ impl NodePatternParser for AnnotationComponent {
             &self.name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         write_to_context(
             &self.value,
             pattern.essential,
             &pattern.compiled_auxiliary_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )
     }
 }
@@ -573,26 +819,26 @@ impl NodePatternParser for AnnotationValuePair {
         verify_match!(
             &self.key,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         verify_match!(
             &self.value,
             &*pattern.compiled_auxiliary_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         write_to_context(
             &self.value,
             pattern.essential,
             &pattern.compiled_auxiliary_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )?;
         write_to_context(
             &self.key,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )
     }
 }
@@ -608,7 +854,7 @@ impl NodePatternParser for Ident {
             &self.name,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )
     }
 }
@@ -623,14 +869,14 @@ impl NodePatternParser for Literal {
         verify_match!(
             &self.value,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
         write_to_context(
             &self.value,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )
     }
 }
@@ -647,7 +893,7 @@ impl NodePatternParser for BinaryExpr {
         verify_match!(
             op,
             &*pattern.compiled_pattern.borrow(),
-            &ctx.parser,
+            ctx,
             pattern.essential
         );
 
@@ -658,7 +904,271 @@ impl NodePatternParser for BinaryExpr {
             op,
             pattern.essential,
             &pattern.compiled_pattern.borrow(),
-            &mut ctx.parser,
+            ctx,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::ressa::test_util::run_java;
+
+    const CONTROLLER: &str = r#"
+    @RestController
+    class OrderController {
+        @PreAuthorize("hasRole('ADMIN')")
+        @GetMapping("/orders")
+        public List<Order> list() {
+            return service.list();
+        }
+
+        @PostMapping("/orders")
+        public void create(Order order) {
+            service.create(order);
+        }
+
+        @DeleteMapping("/orders")
+        public void delete(Order order) {
+            service.delete(order);
+        }
+    }
+    "#;
+
+    #[test]
+    fn negated_subpattern() {
+        let patterns = r##"[{
+            "identifier": "Method",
+            "pattern": "#{method}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [{
+                "identifier": "Annotation",
+                "pattern": "Mapping",
+                "essential": true,
+                "subpatterns": []
+            }, {
+                "identifier": "Annotation",
+                "pattern": "@PreAuthorize",
+                "essential": false,
+                "negated": true,
+                "subpatterns": []
+            }],
+            "callback": "ctx.save(ctx.get_variable(\"method\").unwrap(), #{});"
+        }]"##;
+        let mut found = run_java(CONTROLLER, patterns);
+        found.sort();
+        assert_eq!(vec!["create".to_string(), "delete".to_string()], found);
+    }
+
+    fn count_methods(min_count: &str, max_count: &str) -> Vec<String> {
+        let patterns = format!(
+            r##"[{{
+            "identifier": "ClassOrInterface",
+            "pattern": "#{{class}}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [{{
+                "identifier": "Method",
+                "pattern": "",
+                "essential": false,
+                "min_count": {},
+                "max_count": {},
+                "subpatterns": [{{
+                    "identifier": "Annotation",
+                    "pattern": "@(Post|Delete)Mapping",
+                    "essential": true,
+                    "subpatterns": []
+                }}]
+            }}],
+            "callback": "ctx.save(ctx.get_variable(\"class\").unwrap(), #{{}});"
+        }}]"##,
+            min_count, max_count
+        );
+        run_java(CONTROLLER, &patterns)
+    }
+
+    #[test]
+    fn counted_subpattern() {
+        let controller = vec!["OrderController".to_string()];
+        assert_eq!(controller, count_methods("2", "null"));
+        assert_eq!(controller, count_methods("null", "2"));
+        assert_eq!(controller, count_methods("2", "2"));
+        assert!(count_methods("3", "null").is_empty());
+        assert!(count_methods("null", "1").is_empty());
+    }
+
+    const SENDER: &str = r#"
+    class OrderSender {
+        public void send(Order order, User user) {
+            send("/a", order);
+            send(order, "/b");
+            send("/c", order, user);
+            send(user, "/d", order);
+            post(retries = 3, url = "/e");
+            post(url = "/f");
+            post("/g");
+            flush();
+        }
+
+        public void update(User user, Order order) {}
+
+        public void cancel(Order order) {}
+    }
+    "#;
+
+    fn match_calls(name: &str, argument_matching: &str, subpatterns: &str) -> Vec<String> {
+        let patterns = format!(
+            r##"[{{
+            "identifier": "CallExpr",
+            "pattern": "^{}$",
+            "auxiliary_pattern": "^this$",
+            "essential": true,
+            "language": "Java",
+            "argument_matching": "{}",
+            "subpatterns": {},
+            "callback": "ctx.save(ctx.get_variable(\"url\").unwrap(), #{{}});"
+        }}]"##,
+            name, argument_matching, subpatterns
+        );
+        let mut found = run_java(SENDER, &patterns);
+        found.sort();
+        found
+    }
+
+    fn match_methods(argument_matching: &str, subpatterns: &str) -> Vec<String> {
+        let patterns = format!(
+            r##"[{{
+            "identifier": "Method",
+            "pattern": "#{{method}}",
+            "essential": true,
+            "language": "Java",
+            "argument_matching": "{}",
+            "subpatterns": {},
+            "callback": "ctx.save(ctx.get_variable(\"method\").unwrap(), #{{}});"
+        }}]"##,
+            argument_matching, subpatterns
+        );
+        let mut found = run_java(SENDER, &patterns);
+        found.sort();
+        found
+    }
+
+    const URL_THEN_ORDER: &str = r##"[{
+        "identifier": "Literal",
+        "pattern": "/#{url}([a-z])",
+        "essential": true,
+        "subpatterns": []
+    }, {
+        "identifier": "Ident",
+        "pattern": "^order$",
+        "essential": true,
+        "subpatterns": []
+    }]"##;
+
+    #[test]
+    fn exact_argument_matching() {
+        assert_eq!(vec!["a"], match_calls("send", "exact", URL_THEN_ORDER));
+
+        let subpatterns = r##"[{
+            "identifier": "MethodParam",
+            "pattern": "^order$",
+            "essential": true,
+            "subpatterns": []
+        }, {
+            "identifier": "MethodParam",
+            "pattern": "^user$",
+            "essential": true,
+            "subpatterns": []
+        }]"##;
+        assert_eq!(vec!["send"], match_methods("exact", subpatterns));
+    }
+
+    #[test]
+    fn subsequence_argument_matching() {
+        assert_eq!(
+            vec!["a", "c", "d"],
+            match_calls("send", "subsequence", URL_THEN_ORDER)
+        );
+    }
+
+    #[test]
+    fn non_essential_argument_without_arguments() {
+        let patterns = r##"[{
+            "identifier": "CallExpr",
+            "pattern": "^#{call}(flush)$",
+            "auxiliary_pattern": "^this$",
+            "essential": true,
+            "language": "Java",
+            "argument_matching": "subsequence",
+            "subpatterns": [{
+                "identifier": "Literal",
+                "pattern": "#{url}",
+                "essential": false,
+                "subpatterns": []
+            }],
+            "callback": "ctx.save(ctx.get_variable(\"call\").unwrap(), #{});"
+        }]"##;
+        assert_eq!(vec!["flush"], run_java(SENDER, patterns));
+    }
+
+    #[test]
+    fn unordered_argument_matching() {
+        assert_eq!(
+            vec!["a", "b", "c", "d"],
+            match_calls("send", "unordered", URL_THEN_ORDER)
+        );
+
+        // The first pattern also accepts "order", so it must give way to the second
+        let subpatterns = r##"[{
+            "identifier": "Ident",
+            "pattern": "",
+            "essential": true,
+            "subpatterns": []
+        }, {
+            "identifier": "Ident",
+            "pattern": "^order$",
+            "essential": true,
+            "subpatterns": []
+        }, {
+            "identifier": "Literal",
+            "pattern": "/#{url}([a-z])",
+            "essential": true,
+            "subpatterns": []
+        }]"##;
+        assert_eq!(
+            vec!["c", "d"],
+            match_calls("send", "unordered", subpatterns)
+        );
+    }
+
+    #[test]
+    fn keyword_argument_matching() {
+        let subpatterns = r##"[{
+            "identifier": "Literal",
+            "pattern": "/#{url}([a-z])",
+            "essential": true,
+            "keyword": "url",
+            "subpatterns": []
+        }]"##;
+        assert_eq!(vec!["e", "f"], match_calls("post", "keyword", subpatterns));
+
+        // Unnamed patterns match the parameters left over by the named ones
+        let subpatterns = r##"[{
+            "identifier": "MethodParam",
+            "pattern": "",
+            "auxiliary_pattern": "^User$",
+            "essential": true,
+            "keyword": "user",
+            "subpatterns": []
+        }, {
+            "identifier": "MethodParam",
+            "pattern": "^order$",
+            "essential": true,
+            "subpatterns": []
+        }]"##;
+        assert_eq!(
+            vec!["send", "update"],
+            match_methods("keyword", subpatterns)
+        );
+    }
+}
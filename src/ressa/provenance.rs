@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::NodeView;
+
+/// Provenance of every object in a ReSSA result, keyed by object name
+pub type RessaProvenance = BTreeMap<String, ObjectProvenance>;
+
+/// The match that wrote a value to the context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    /// Index of the root pattern being applied
+    pub pattern: usize,
+
+    /// Type of the node the pattern matched, as named in ReSSA patterns
+    pub kind: String,
+
+    /// File the node is in, if known
+    pub file: Option<String>,
+
+    /// Line range of the node, if it records one (statements and expressions do not)
+    pub line_begin: Option<i64>,
+    pub line_end: Option<i64>,
+
+    /// Line range of the closest enclosing node with one, like the method holding a call
+    pub enclosing_line_begin: Option<i64>,
+    pub enclosing_line_end: Option<i64>,
+}
+
+impl Provenance {
+    /// Locate the node described by the view, falling back to its enclosing scopes for the file
+    /// it is in
+    pub fn new(pattern: usize, node: &NodeView) -> Provenance {
+        // Scopes are outermost first, so search them innermost first
+        let parents = node.parents();
+        let mut scopes = parents.iter().rev();
+        let file = std::iter::once(node)
+            .chain(scopes.clone())
+            .find_map(NodeView::file);
+        let enclosing = scopes.find(|scope| scope.line_begin().is_some());
+        Provenance {
+            pattern,
+            kind: node.kind(),
+            file,
+            line_begin: node.line_begin(),
+            line_end: node.line_end(),
+            enclosing_line_begin: enclosing.and_then(NodeView::line_begin),
+            enclosing_line_end: enclosing.and_then(NodeView::line_end),
+        }
+    }
+}
+
+/// Provenance of one object in the result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ObjectProvenance {
+    /// Every distinct match that saved the object, in order
+    pub saved_by: Vec<Provenance>,
+
+    /// For each attribute of the object, the match whose save first included it
+    pub attributes: BTreeMap<String, Provenance>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ressa::{run_ressa_parse_with_options, NodePattern, RessaOptions};
+    use crate::test_util::parse_java;
+
+    #[test]
+    fn records_matching_node() {
+        let code = r#"
+        class OrderService {
+            public void place(Order order) {
+                repository.save(order);
+            }
+
+            public void cancel(Order order) {
+                repository.delete(order);
+            }
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "Method",
+            "pattern": "#{method}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "ctx.make_transient(\"scratch\");"
+        }, {
+            "identifier": "CallExpr",
+            "pattern": "#{op}(save|delete)",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "let ops = ctx.get_or_save(\"repository\", #{}); ops[ctx.get_variable(\"op\").unwrap()] = true; ctx.save(\"repository\", ops);"
+        }]"##;
+        let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+        let options = RessaOptions {
+            provenance: true,
+            ..Default::default()
+        };
+        let report = run_ressa_parse_with_options(&parse_java(code), patterns, options);
+        let (result, provenance) = (report.result, report.provenance.unwrap());
+
+        // Transient objects are left out, like in the result
+        assert!(result.contains_key("repository"));
+        assert_eq!(vec!["repository"], provenance.keys().collect::<Vec<_>>());
+
+        let repository = &provenance["repository"];
+        assert_eq!(2, repository.saved_by.len());
+        let save = &repository.attributes["save"];
+        let delete = &repository.attributes["delete"];
+        assert_eq!(1, save.pattern);
+        assert_eq!("CallExpr", save.kind);
+        assert_eq!(Some("OrderService.java".to_string()), save.file);
+        // Calls do not record lines, unlike the methods holding them
+        assert_eq!((None, None), (save.line_begin, save.line_end));
+        assert_eq!(
+            (Some(3), Some(5)),
+            (save.enclosing_line_begin, save.enclosing_line_end)
+        );
+        assert_eq!(
+            (Some(7), Some(9)),
+            (delete.enclosing_line_begin, delete.enclosing_line_end)
+        );
+    }
+}
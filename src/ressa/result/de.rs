@@ -0,0 +1,334 @@
+use std::vec;
+
+use rune::runtime::{AccessError, Object, Shared, Value, VariantData};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, Error as _, IntoDeserializer, MapAccess,
+    SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+
+use super::{Error, RessaResult};
+
+/// Deserialize the objects of a ReSSA result into `T`, as a map from object name to object.
+/// Rune values are read as the plain data they serialize to (see `PlainValue`).
+pub fn from_ressa_result<T: DeserializeOwned>(result: RessaResult) -> Result<T, Error> {
+    let mut objects = Object::new();
+    for (name, value) in result.into_inner() {
+        objects.insert(name, value);
+    }
+    from_value(Value::Object(Shared::new(objects)))
+}
+
+/// Deserialize a Rune value into `T`
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    T::deserialize(ValueDeserializer(value))
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Deserialize {
+            path: "$".into(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+impl Error {
+    /// Locate a deserialization error within the named member (`.key` or `[index]`)
+    fn within(self, member: &str) -> Self {
+        match self {
+            Error::Deserialize { path, message } => Error::Deserialize {
+                path: format!("${}{}", member, &path[1..]),
+                message,
+            },
+            err => err,
+        }
+    }
+}
+
+fn access<T>(result: Result<T, AccessError>) -> Result<T, Error> {
+    result.map_err(Error::custom)
+}
+
+struct ValueDeserializer(Value);
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Unit | Value::UnitStruct(_) => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Byte(b) => visitor.visit_u8(b),
+            Value::Char(c) => visitor.visit_char(c),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Float(f) => visitor.visit_f64(f),
+            Value::StaticString(string) => visitor.visit_str(string.as_ref()),
+            Value::String(string) => visitor.visit_string(access(string.borrow_ref())?.clone()),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(access(bytes.borrow_ref())?.to_vec()),
+            Value::Vec(vec) => visit_seq(access(vec.borrow_ref())?.to_vec(), visitor),
+            Value::Tuple(tuple) => visit_seq(access(tuple.borrow_ref())?.to_vec(), visitor),
+            Value::TupleStruct(tuple) => {
+                visit_seq(access(tuple.borrow_ref())?.data().to_vec(), visitor)
+            }
+            Value::Object(object) => visit_object(&*access(object.borrow_ref())?, visitor),
+            Value::Struct(object) => visit_object(access(object.borrow_ref())?.data(), visitor),
+            Value::Option(option) => match access(option.borrow_ref())?.clone() {
+                Some(value) => visitor.visit_some(ValueDeserializer(value)),
+                None => visitor.visit_none(),
+            },
+            value @ (Value::Result(_) | Value::Variant(_)) => {
+                match EnumDeserializer::from_value(value)? {
+                    EnumDeserializer {
+                        variant,
+                        content: None,
+                    } => visitor.visit_string(variant),
+                    EnumDeserializer {
+                        variant,
+                        content: Some(content),
+                    } => visitor.visit_map(MapDeserializer::new(vec![(variant, content)])),
+                }
+            }
+            value => Err(Error::custom(format!(
+                "cannot deserialize {}",
+                value
+                    .type_info()
+                    .map(|info| info.to_string())
+                    .unwrap_or_else(|_| "unknown value".into())
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Unit => visitor.visit_none(),
+            Value::Option(option) => match access(option.borrow_ref())?.clone() {
+                Some(value) => visitor.visit_some(ValueDeserializer(value)),
+                None => visitor.visit_none(),
+            },
+            value => visitor.visit_some(ValueDeserializer(value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(EnumDeserializer::from_value(self.0)?)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(values: Vec<Value>, visitor: V) -> Result<V::Value, Error> {
+    visitor.visit_seq(SeqDeserializer {
+        values: values.into_iter(),
+        index: 0,
+    })
+}
+
+fn visit_object<'de, V: Visitor<'de>>(object: &Object, visitor: V) -> Result<V::Value, Error> {
+    let entries = object
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    visitor.visit_map(MapDeserializer::new(entries))
+}
+
+struct SeqDeserializer {
+    values: vec::IntoIter<Value>,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.values.next() {
+            Some(value) => {
+                let index = self.index;
+                self.index += 1;
+                seed.deserialize(ValueDeserializer(value))
+                    .map(Some)
+                    .map_err(|err| err.within(&format!("[{}]", index)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct MapDeserializer {
+    entries: vec::IntoIter<(String, Value)>,
+    /// Entry whose key was just deserialized
+    current: Option<(String, Value)>,
+}
+
+impl MapDeserializer {
+    fn new(entries: Vec<(String, Value)>) -> MapDeserializer {
+        MapDeserializer {
+            entries: entries.into_iter(),
+            current: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                let deserialized =
+                    seed.deserialize(IntoDeserializer::<Error>::into_deserializer(key.clone()))?;
+                self.current = Some((key, value));
+                Ok(Some(deserialized))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .current
+            .take()
+            .ok_or_else(|| Error::custom("value requested before its key"))?;
+        seed.deserialize(ValueDeserializer(value))
+            .map_err(|err| err.within(&format!(".{}", key)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// An enum variant: a plain string for unit variants, a single-entry map from variant name to
+/// content, or a Rune result or variant
+struct EnumDeserializer {
+    variant: String,
+    content: Option<Value>,
+}
+
+impl EnumDeserializer {
+    fn from_value(value: Value) -> Result<EnumDeserializer, Error> {
+        let (variant, content) = match value {
+            Value::StaticString(string) => (string.as_ref().to_string(), None),
+            Value::String(string) => (access(string.borrow_ref())?.clone(), None),
+            Value::Object(object) => {
+                let object = access(object.borrow_ref())?;
+                let mut entries = object.iter();
+                match (entries.next(), entries.next()) {
+                    (Some((variant, content)), None) => (variant.clone(), Some(content.clone())),
+                    _ => {
+                        return Err(Error::invalid_length(
+                            object.len(),
+                            &"an object with a single variant",
+                        ))
+                    }
+                }
+            }
+            Value::Result(result) => match access(result.borrow_ref())?.clone() {
+                Ok(content) => ("Ok".to_string(), Some(content)),
+                Err(content) => ("Err".to_string(), Some(content)),
+            },
+            Value::Variant(variant) => {
+                let variant = access(variant.borrow_ref())?;
+                let name = variant
+                    .rtti()
+                    .item
+                    .last()
+                    .map(|name| name.to_string())
+                    .ok_or_else(|| Error::custom("unnamed variant"))?;
+                let content = match variant.data() {
+                    VariantData::Unit => None,
+                    VariantData::Tuple(tuple) => Some(Value::Tuple(Shared::new(tuple.clone()))),
+                    VariantData::Struct(object) => Some(Value::Object(Shared::new(object.clone()))),
+                };
+                (name, content)
+            }
+            _ => {
+                return Err(Error::invalid_type(
+                    Unexpected::Other("non-enum value"),
+                    &"a string or an object with a single variant",
+                ))
+            }
+        };
+        Ok(EnumDeserializer { variant, content })
+    }
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = EnumDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(
+            self.variant.clone(),
+        ))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for EnumDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.content {
+            None | Some(Value::Unit) => Ok(()),
+            Some(_) => Err(Error::invalid_type(
+                Unexpected::NewtypeVariant,
+                &"a unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        let member = format!(".{}", self.variant);
+        seed.deserialize(ValueDeserializer(self.content.unwrap_or(Value::Unit)))
+            .map_err(|err| err.within(&member))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        let member = format!(".{}", self.variant);
+        de::Deserializer::deserialize_seq(
+            ValueDeserializer(self.content.unwrap_or(Value::Unit)),
+            visitor,
+        )
+        .map_err(|err| err.within(&member))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let member = format!(".{}", self.variant);
+        de::Deserializer::deserialize_map(
+            ValueDeserializer(self.content.unwrap_or(Value::Unit)),
+            visitor,
+        )
+        .map_err(|err| err.within(&member))
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
+use rune::runtime::{Object, Shared, Value};
+
+mod de;
+pub use de::*;
+
+mod ser;
+pub use ser::*;
+
+/// Output type from the library: the objects saved by callbacks, by name.
+///
+/// This used to be an alias of `BTreeMap<String, Value>`, and is now a type of its own so that it
+/// can be serialized as plain data. It dereferences to the map, so looking up and iterating
+/// objects works as before, while code naming the map type converts with `into_inner` and `From`.
+#[derive(Debug, Clone, Default)]
+pub struct RessaResult(BTreeMap<String, Value>);
+
+impl RessaResult {
+    pub fn into_inner(self) -> BTreeMap<String, Value> {
+        self.0
+    }
+}
+
+impl Deref for RessaResult {
+    type Target = BTreeMap<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for RessaResult {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<BTreeMap<String, Value>> for RessaResult {
+    fn from(objects: BTreeMap<String, Value>) -> Self {
+        RessaResult(objects)
+    }
+}
+
+impl FromIterator<(String, Value)> for RessaResult {
+    fn from_iter<T: IntoIterator<Item = (String, Value)>>(iter: T) -> Self {
+        RessaResult(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for RessaResult {
+    type Item = (String, Value);
+    type IntoIter = std::collections::btree_map::IntoIter<String, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Errors encounterable accessing Rune data
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Missing key {0}")]
+    MissingKey(String),
+    #[error("Invalid key {0}")]
+    InvalidKey(String),
+    #[error("Invalid type {0}")]
+    InvalidType(String),
+    #[error("Could not convert the Rune value")]
+    RuneAcquisition,
+    #[error("Could not access the Rune value: {0}")]
+    RuneAccess(rune::runtime::AccessError),
+    /// Failure deserializing a value, at a path like `$.orders.items[2]`
+    #[error("{message} (at {path})")]
+    Deserialize { path: String, message: String },
+}
+
+/// Coerces a `runestick::Value` into a `T`
+pub fn coerce_primitive<I, T, E>(value: &Value, into: I) -> Result<T, Error>
+where
+    I: FnOnce(Value) -> Result<T, E>,
+{
+    into(value.clone()).map_err(|_| Error::RuneAcquisition)
+}
+
+/// Extracts a value from the given object at key `name` and coerces it into `T`
+pub fn extract_primitive<I, T, E>(
+    obj: &BTreeMap<String, Value>,
+    name: &str,
+    into: I,
+) -> Result<T, Error>
+where
+    I: FnOnce(Value) -> Result<T, E>,
+{
+    obj.get(name)
+        .ok_or_else(|| Error::MissingKey(name.to_string()))
+        .and_then(|value| coerce_primitive(value, into))
+}
+
+/// Coerces a `runestick::Value` into a `Shared<T>`
+pub fn coerce<I, T, E>(value: &Value, into: I) -> Result<T, Error>
+where
+    I: FnOnce(Value) -> Result<Shared<T>, E>,
+{
+    match into(value.clone()) {
+        Ok(obj) => obj.take().map_err(Error::RuneAccess),
+        Err(_) => Err(Error::RuneAcquisition),
+    }
+}
+
+/// Extracts a value from the given object at key `name` and coerces it into `T`
+pub fn extract<I, T, E>(obj: &BTreeMap<String, Value>, name: &str, into: I) -> Result<T, Error>
+where
+    I: FnOnce(Value) -> Result<Shared<T>, E>,
+{
+    obj.get(name)
+        .ok_or_else(|| Error::MissingKey(name.to_string()))
+        .and_then(|value| coerce(value, into))
+}
+
+/// Coerces a `runestick::Object` into a `BTreeMap<String, Value>`
+pub fn extract_object(obj: Object) -> BTreeMap<String, Value> {
+    obj.into_inner()
+}
+
+/// Gets the `runestick::Vec` at key `name` and coerces it into a `Vec<T>`
+pub fn extract_vec<I, T, E>(
+    obj: &BTreeMap<String, Value>,
+    name: &str,
+    into: I,
+) -> Result<Vec<T>, Error>
+where
+    I: Fn(Value) -> Result<Shared<T>, E> + Copy,
+{
+    let (result, err): (Vec<Option<T>>, Vec<Option<Error>>) = extract(obj, name, Value::into_vec)?
+        .into_iter()
+        // `into` has the Copy trait bound so it can be called multiple times when
+        // captured by map's `FnMut` argument
+        .map(|elem| coerce(&elem, into))
+        .map(|obj| match obj {
+            Ok(obj) => (Some(obj), None),
+            Err(err) => (None, Some(err)),
+        })
+        .unzip();
+
+    // Short-circuit any errors that occurred
+    if let Some(Some(bad_result)) = err.into_iter().find(|err| err.is_some()) {
+        return Err(bad_result);
+    }
+
+    // Coerce results out of the vec
+    Ok(result.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::{from_ressa_result, Error, RessaResult};
+    use crate::ressa::{run_ressa_parse, NodePattern};
+    use crate::test_util::parse_java;
+
+    fn run(code: &str, patterns: &str) -> RessaResult {
+        let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+        run_ressa_parse(&mut parse_java(code), patterns)
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Repository {
+        name: String,
+        operations: BTreeMap<String, bool>,
+        tags: Vec<String>,
+        owner: Option<String>,
+        kind: Kind,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Jpa,
+        Custom { base: String },
+    }
+
+    const CODE: &str = r#"
+    class OrderService {
+        public void place(Order order) {
+            repository.save(order);
+            repository.delete(order);
+        }
+    }
+    "#;
+
+    #[test]
+    fn deserializes_saved_objects() {
+        let patterns = r##"[{
+            "identifier": "CallExpr",
+            "pattern": "#{op}(save|delete)",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "let repo = ctx.get_or_save(\"repository\", #{name: \"repository\", operations: #{}, tags: [\"jpa\", \"orders\"], owner: None, kind: \"Jpa\"}); repo.operations[ctx.get_variable(\"op\").unwrap()] = true; ctx.save(\"repository\", repo);"
+        }]"##;
+        let result = run(CODE, patterns);
+        assert_eq!(
+            json!({"repository": {
+                "name": "repository",
+                "operations": {"save": true, "delete": true},
+                "tags": ["jpa", "orders"],
+                "owner": null,
+                "kind": "Jpa"
+            }}),
+            serde_json::to_value(&result).unwrap()
+        );
+
+        let repositories: BTreeMap<String, Repository> = from_ressa_result(result).unwrap();
+        assert_eq!(
+            Repository {
+                name: "repository".into(),
+                operations: vec![("delete".into(), true), ("save".into(), true)]
+                    .into_iter()
+                    .collect(),
+                tags: vec!["jpa".into(), "orders".into()],
+                owner: None,
+                kind: Kind::Jpa,
+            },
+            repositories["repository"]
+        );
+    }
+
+    #[test]
+    fn reports_error_paths() {
+        let patterns = r##"[{
+            "identifier": "CallExpr",
+            "pattern": "#{op}(save)",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [],
+            "callback": "ctx.save(\"repository\", #{name: \"repository\", operations: #{}, tags: [\"jpa\", 1], kind: \"Jpa\"});"
+        }]"##;
+        let err =
+            from_ressa_result::<BTreeMap<String, Repository>>(run(CODE, patterns)).unwrap_err();
+        match err {
+            Error::Deserialize { path, .. } => assert_eq!("$.repository.tags[1]", path),
+            err => panic!("unexpected error {}", err),
+        }
+
+        let patterns = patterns
+            .replace("\\\"Jpa\\\"", "#{Custom: #{}}")
+            .replace(", 1]", "]");
+        let err =
+            from_ressa_result::<BTreeMap<String, Repository>>(run(CODE, &patterns)).unwrap_err();
+        assert_eq!(
+            "missing field `base` (at $.repository.kind.Custom)",
+            err.to_string()
+        );
+    }
+}
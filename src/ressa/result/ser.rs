@@ -0,0 +1,123 @@
+use rune::runtime::{Object, Value, VariantData};
+use serde::ser::{Error as _, Serialize, SerializeMap, Serializer};
+
+use super::RessaResult;
+
+/// Serializes the objects as a map from object name to plain data (see `PlainValue`)
+impl Serialize for RessaResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter().map(|(name, value)| (name, PlainValue(value))))
+    }
+}
+
+/// Serializes a Rune value as plain data, independent of how Rune represents it:
+/// - Unit values, unit structs and empty options are null; other options are their content
+/// - Strings and characters are strings, and bytes are sequences of numbers
+/// - Vectors, tuples and tuple structs are sequences
+/// - Objects and structs are maps
+/// - Results and enum variants are externally tagged (`"Variant"`, `{"Variant": ...}`)
+///
+/// Values that are not data, like functions and iterators, cannot be serialized.
+pub struct PlainValue<'a>(pub &'a Value);
+
+impl Serialize for PlainValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Unit | Value::UnitStruct(_) => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Byte(b) => serializer.serialize_u8(*b),
+            Value::Char(c) => serializer.serialize_char(*c),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Float(f) => serializer.serialize_f64(*f),
+            Value::StaticString(string) => serializer.serialize_str(string.as_ref()),
+            Value::String(string) => {
+                serializer.serialize_str(&string.borrow_ref().map_err(S::Error::custom)?)
+            }
+            Value::Bytes(bytes) => {
+                serializer.collect_seq(bytes.borrow_ref().map_err(S::Error::custom)?.iter())
+            }
+            Value::Vec(vec) => {
+                serialize_seq(&vec.borrow_ref().map_err(S::Error::custom)?, serializer)
+            }
+            Value::Tuple(tuple) => {
+                serialize_seq(&tuple.borrow_ref().map_err(S::Error::custom)?, serializer)
+            }
+            Value::TupleStruct(tuple) => serialize_seq(
+                tuple.borrow_ref().map_err(S::Error::custom)?.data(),
+                serializer,
+            ),
+            Value::Object(object) => {
+                serialize_object(&*object.borrow_ref().map_err(S::Error::custom)?, serializer)
+            }
+            Value::Struct(object) => serialize_object(
+                object.borrow_ref().map_err(S::Error::custom)?.data(),
+                serializer,
+            ),
+            Value::Option(option) => match &*option.borrow_ref().map_err(S::Error::custom)? {
+                Some(value) => serializer.serialize_some(&PlainValue(value)),
+                None => serializer.serialize_none(),
+            },
+            Value::Result(result) => match &*result.borrow_ref().map_err(S::Error::custom)? {
+                Ok(value) => serialize_tagged("Ok", &PlainValue(value), serializer),
+                Err(value) => serialize_tagged("Err", &PlainValue(value), serializer),
+            },
+            Value::Variant(variant) => {
+                let variant = variant.borrow_ref().map_err(S::Error::custom)?;
+                let name = match variant.rtti().item.last() {
+                    Some(name) => name.to_string(),
+                    None => return Err(S::Error::custom("cannot serialize unnamed variant")),
+                };
+                match variant.data() {
+                    VariantData::Unit => serializer.serialize_str(&name),
+                    VariantData::Tuple(tuple) => {
+                        serialize_tagged(&name, &PlainSeq(tuple), serializer)
+                    }
+                    VariantData::Struct(object) => {
+                        serialize_tagged(&name, &PlainObject(object), serializer)
+                    }
+                }
+            }
+            value => Err(S::Error::custom(format!(
+                "cannot serialize {}",
+                value
+                    .type_info()
+                    .map(|info| info.to_string())
+                    .unwrap_or_else(|_| "unknown value".into())
+            ))),
+        }
+    }
+}
+
+struct PlainSeq<'a>(&'a [Value]);
+
+impl Serialize for PlainSeq<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_seq(self.0, serializer)
+    }
+}
+
+struct PlainObject<'a>(&'a Object);
+
+impl Serialize for PlainObject<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_object(self.0, serializer)
+    }
+}
+
+fn serialize_seq<S: Serializer>(values: &[Value], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(PlainValue))
+}
+
+fn serialize_object<S: Serializer>(object: &Object, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(object.iter().map(|(key, value)| (key, PlainValue(value))))
+}
+
+fn serialize_tagged<S: Serializer, T: Serialize>(
+    tag: &str,
+    content: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry(tag, content)?;
+    map.end()
+}
@@ -0,0 +1,12 @@
+use super::{run_ressa_parse, NodePattern};
+use crate::test_util::parse_java;
+
+/// Run JSON-encoded patterns against a Java source file, returning the names of the saved objects
+pub(crate) fn run_java(code: &str, patterns: &str) -> Vec<String> {
+    let mut modules = parse_java(code);
+    let patterns: Vec<NodePattern> = serde_json::from_str(patterns).unwrap();
+    run_ressa_parse(&mut modules, patterns)
+        .into_inner()
+        .into_keys()
+        .collect()
+}
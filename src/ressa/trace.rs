@@ -0,0 +1,243 @@
+use serde::Serialize;
+
+use super::{ArgumentMatching, NodePattern, NodeType, NodeView, Provenance};
+
+/// Record of how each root pattern was applied during a ReSSA run, for debugging patterns
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RessaTrace {
+    pub patterns: Vec<PatternTrace>,
+}
+
+/// Everything that happened while applying one root pattern, in order
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PatternTrace {
+    /// Index of the root pattern
+    pub pattern: usize,
+    pub events: Vec<TraceEvent>,
+}
+
+/// Something that happened while matching. `depth` is the nesting of the match the event
+/// happened in, where the root pattern's match is 1.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// A node of the pattern's type was checked against the pattern
+    Considered {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+        node: TracedNode,
+    },
+
+    /// A pattern's regexes failed to compile, so it cannot match any node
+    InvalidPattern {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+        error: String,
+    },
+
+    /// A pattern's regex failed on a node's string
+    RegexFailed {
+        depth: i32,
+        regex: String,
+        input: String,
+    },
+
+    /// A subpattern was not found (or not found the required number of times), failing its parent
+    SubpatternAborted {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+        reason: AbortReason,
+    },
+
+    /// Argument subpatterns could not be paired with a node's arguments
+    ArgumentsUnmatched {
+        depth: i32,
+        mode: ArgumentMatching,
+        patterns: usize,
+        arguments: usize,
+    },
+
+    /// A matched node did not satisfy the pattern's constraints
+    ConstraintFailed {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+    },
+
+    /// A pattern's callback failed (or panicked), failing the match
+    CallbackFailed {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+        error: String,
+    },
+
+    /// The considered node matched the pattern
+    Matched {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+    },
+
+    /// The considered node did not match the pattern
+    Rejected {
+        #[serde(flatten)]
+        pattern: TracedPattern,
+    },
+}
+
+/// Why a subpattern failed its parent
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbortReason {
+    /// An essential subpattern was not found
+    NotFound,
+
+    /// A negated or counted subpattern was found the wrong number of times
+    CountUnsatisfied,
+}
+
+/// The pattern an event concerns
+#[derive(Debug, Clone, Serialize)]
+pub struct TracedPattern {
+    pub depth: i32,
+    pub identifier: NodeType,
+    pub pattern: String,
+    pub auxiliary_pattern: Option<String>,
+}
+
+impl TracedPattern {
+    pub fn new(pattern: &NodePattern, depth: i32) -> TracedPattern {
+        TracedPattern {
+            depth,
+            identifier: pattern.identifier,
+            pattern: pattern.pattern.clone(),
+            auxiliary_pattern: pattern.auxiliary_pattern.clone(),
+        }
+    }
+}
+
+/// The node an event concerns
+#[derive(Debug, Clone, Serialize)]
+pub struct TracedNode {
+    pub kind: String,
+    pub name: String,
+    pub file: Option<String>,
+    pub line_begin: Option<i64>,
+    pub line_end: Option<i64>,
+}
+
+impl From<&NodeView> for TracedNode {
+    fn from(node: &NodeView) -> Self {
+        let Provenance {
+            kind,
+            file,
+            line_begin,
+            line_end,
+            ..
+        } = Provenance::new(0, node);
+        TracedNode {
+            kind,
+            name: node.name(),
+            file,
+            line_begin,
+            line_end,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::ressa::{run_ressa_parse_with_options, RessaOptions};
+    use crate::test_util::parse_java;
+
+    /// The events of the first root pattern, as JSON
+    fn trace_events(code: &str, patterns: &str) -> Vec<Value> {
+        let options = RessaOptions {
+            trace: true,
+            ..Default::default()
+        };
+        let patterns = serde_json::from_str(patterns).unwrap();
+        let report = run_ressa_parse_with_options(&parse_java(code), patterns, options);
+        let trace = serde_json::to_value(report.trace.unwrap()).unwrap();
+        trace["patterns"][0]["events"].as_array().unwrap().clone()
+    }
+
+    fn events_of<'a>(events: &'a [Value], event: &str) -> Vec<&'a Value> {
+        events.iter().filter(|e| e["event"] == event).collect()
+    }
+
+    #[test]
+    fn traces_failed_matches() {
+        let code = r#"
+        class OrderController {
+            @GetMapping("/orders")
+            public void list() {}
+
+            public void helper() {}
+
+            @PostMapping("/orders")
+            public void create() {}
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "Method",
+            "pattern": "#{method}",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": [{
+                "identifier": "Annotation",
+                "pattern": "@GetMapping",
+                "essential": true,
+                "subpatterns": []
+            }],
+            "callback": "if ctx.get_variable(\"method\").unwrap() == \"list\" { panic(\"rejected\"); }"
+        }]"##;
+        let events = trace_events(code, patterns);
+
+        let considered = events_of(&events, "considered");
+        assert!(considered
+            .iter()
+            .any(|e| e["node"]["name"] == "helper" && e["node"]["line_begin"] == 6));
+
+        // "@PostMapping" fails the regex, and "helper" has no annotation at all
+        assert!(events_of(&events, "regex_failed")
+            .iter()
+            .any(|e| e["regex"] == "@GetMapping" && e["input"] == "@PostMapping"));
+        let aborted = events_of(&events, "subpattern_aborted");
+        assert!(!aborted.is_empty());
+        assert!(aborted.iter().all(|e| e["identifier"] == "Annotation"
+            && e["reason"] == "not_found"
+            && e["depth"] == 1));
+
+        let failed = events_of(&events, "callback_failed");
+        assert!(!failed.is_empty());
+        assert!(failed.iter().all(|e| e["identifier"] == "Method"));
+        assert!(events_of(&events, "matched")
+            .iter()
+            .all(|e| e["identifier"] == json!("Annotation")));
+    }
+
+    #[test]
+    fn traces_invalid_patterns_once() {
+        let code = r#"
+        class OrderService {
+            public void place() {}
+
+            public void cancel() {}
+        }
+        "#;
+        let patterns = r##"[{
+            "identifier": "Method",
+            "pattern": "#{method}(place",
+            "essential": true,
+            "language": "Java",
+            "subpatterns": []
+        }]"##;
+        let events = trace_events(code, patterns);
+
+        let invalid = events_of(&events, "invalid_pattern");
+        assert_eq!(1, invalid.len());
+        assert_eq!("#{method}(place", invalid[0]["pattern"]);
+        assert!(events_of(&events, "considered").is_empty());
+    }
+}
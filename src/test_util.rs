@@ -0,0 +1,31 @@
+use crate::{convert_trees_to_laast, parse_ast, ModuleComponent, ParsedTree};
+use rust_code_analysis::AstPayload;
+
+/// Lower a single Java source file into its modules
+pub(crate) fn parse_java(code: &str) -> Vec<ModuleComponent> {
+    parse_files(&[("OrderService.java", code)])
+}
+
+/// Lower source files, named with their language's extension, into their modules. Files are
+/// grouped into modules by directory, and files without one are in the "orders" module.
+pub(crate) fn parse_files(files: &[(&str, &str)]) -> Vec<ModuleComponent> {
+    let trees = files
+        .iter()
+        .map(|(file_name, code)| {
+            let (ast, lang) = parse_ast(AstPayload {
+                id: "".to_owned(),
+                file_name: file_name.to_string(),
+                code: code.to_string(),
+                comment: false,
+                span: true,
+            })
+            .unwrap();
+            let module_name = match file_name.rsplit_once('/') {
+                Some((directory, _)) => directory,
+                None => "orders",
+            };
+            ParsedTree::new(ast, lang, module_name.into(), file_name.into())
+        })
+        .collect();
+    convert_trees_to_laast(trees).unwrap()
+}